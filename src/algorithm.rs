@@ -0,0 +1,358 @@
+use std::fmt::Debug;
+
+use crate::alignment::{align_motifs_distance, Hit};
+use crate::discriminative::EnrichedKmer;
+use crate::sequence::{sequences_of, MotifInstance, SequenceRecord, Strand};
+use crate::site_model::SiteModel;
+use crate::{
+    run_discriminative_motif_search, run_expectation_maximization, run_gibbs_sampler,
+    run_median_string, run_randomized_motif_search, Error, SearchOptions,
+};
+
+/// What every algorithm is given besides the sequences
+#[derive(Debug, Clone, Default)]
+pub struct SearchConfig {
+    /// motif length
+    pub k: usize,
+    pub options: SearchOptions,
+}
+
+/// Sites of the motif found by an algorithm, with whatever extra evidence the algorithm gives for them
+#[derive(Debug, Clone, Default)]
+pub struct MotifResult {
+    pub motifs: Vec<MotifInstance>,
+    /// posterior of each site (expectation maximization)
+    pub site_posteriors: Option<Vec<f64>>,
+    /// log likelihood of the fitted motif against the background (expectation maximization)
+    pub log_likelihood: Option<f64>,
+    /// enrichment of the motif against the control set (discriminative)
+    pub enrichment: Option<EnrichedKmer>,
+    /// matches of the pattern in the sequences (pattern search)
+    pub hits: Option<Vec<Hit>>,
+    /// score of the best run after every iteration (sampling searches and expectation maximization)
+    pub score_trace: Option<Vec<f64>>,
+}
+
+impl MotifResult {
+    pub fn new(motifs: Vec<MotifInstance>) -> MotifResult {
+        MotifResult {
+            motifs,
+            ..Default::default()
+        }
+    }
+
+    /// Score compared against the motifs found in shuffled sequences, lower is better
+    ///
+    /// When the site model lets the number of sites vary, this is the likelihood score the searches rank by, since
+    /// a column score would rate a single site as a perfect motif. A result without sites has the worst score.
+    pub(crate) fn null_score(
+        &self,
+        sequences: &[SequenceRecord],
+        config: &SearchConfig,
+    ) -> Result<f64, Error> {
+        if let Some(enrichment) = &self.enrichment {
+            return Ok(enrichment.p_value);
+        }
+        if self.motifs.is_empty() {
+            return Ok(f64::MAX);
+        }
+        let options = &config.options;
+        match options.site_model {
+            SiteModel::Oops => Ok(options.scoring.cost(&self.motifs, &options.background)),
+            site_model => site_model.score_sites(
+                &self.motifs,
+                &sequences_of(sequences),
+                config.k,
+                options.both_strands,
+                options.scoring,
+                &options.background,
+            ),
+        }
+    }
+}
+
+/// A way of finding a motif in a set of sequences
+///
+/// The parameters of the algorithm itself, such as the number of runs, belong to the type that implements it,
+/// so every algorithm can be run and compared the same way.
+pub trait MotifAlgorithm: Debug + Send + Sync {
+    /// Name of the algorithm in reports
+    fn name(&self) -> &str;
+
+    /// Find one motif in the sequences, placing its sites by their index in `sequences`
+    fn discover(
+        &self,
+        sequences: &[SequenceRecord],
+        config: &SearchConfig,
+    ) -> Result<MotifResult, Error>;
+}
+
+/// Run every algorithm on the same sequences, returning their results in the same order
+pub fn run_algorithms(
+    algorithms: &[Box<dyn MotifAlgorithm>],
+    sequences: &[SequenceRecord],
+    config: &SearchConfig,
+) -> Result<Vec<MotifResult>, Error> {
+    algorithms
+        .iter()
+        .map(|algorithm| algorithm.discover(sequences, config))
+        .collect()
+}
+
+#[derive(Debug, Clone)]
+pub struct GibbsSampler {
+    pub runs: usize,
+    pub iterations: usize,
+}
+
+impl MotifAlgorithm for GibbsSampler {
+    fn name(&self) -> &str {
+        "Gibbs Sampler"
+    }
+
+    fn discover(
+        &self,
+        sequences: &[SequenceRecord],
+        config: &SearchConfig,
+    ) -> Result<MotifResult, Error> {
+        let result = run_gibbs_sampler(
+            &sequences_of(sequences),
+            config.k,
+            self.runs,
+            self.iterations,
+            &config.options,
+        )?;
+        Ok(MotifResult {
+            score_trace: Some(result.score_trace),
+            ..MotifResult::new(result.motifs)
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RandomizedMotifSearch {
+    pub runs: usize,
+}
+
+impl MotifAlgorithm for RandomizedMotifSearch {
+    fn name(&self) -> &str {
+        "Randomized Motif Search"
+    }
+
+    fn discover(
+        &self,
+        sequences: &[SequenceRecord],
+        config: &SearchConfig,
+    ) -> Result<MotifResult, Error> {
+        let result = run_randomized_motif_search(
+            &sequences_of(sequences),
+            config.k,
+            self.runs,
+            &config.options,
+        )?;
+        Ok(MotifResult {
+            score_trace: Some(result.score_trace),
+            ..MotifResult::new(result.motifs)
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ExpectationMaximization {
+    pub runs: usize,
+    pub iterations: usize,
+}
+
+impl MotifAlgorithm for ExpectationMaximization {
+    fn name(&self) -> &str {
+        "Expectation Maximization"
+    }
+
+    fn discover(
+        &self,
+        sequences: &[SequenceRecord],
+        config: &SearchConfig,
+    ) -> Result<MotifResult, Error> {
+        let result = run_expectation_maximization(
+            &sequences_of(sequences),
+            config.k,
+            self.runs,
+            self.iterations,
+            &config.options,
+        )?;
+        // keep the posterior of the site reported for each sequence
+        let site_posteriors = result
+            .posteriors
+            .iter()
+            .filter(|posteriors| !posteriors.is_empty())
+            .map(|posteriors| posteriors.iter().cloned().fold(0.0, f64::max))
+            .collect();
+        Ok(MotifResult {
+            site_posteriors: Some(site_posteriors),
+            log_likelihood: Some(result.log_likelihood),
+            score_trace: Some(result.score_trace),
+            ..MotifResult::new(result.motifs)
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MedianString;
+
+impl MotifAlgorithm for MedianString {
+    fn name(&self) -> &str {
+        "Median String"
+    }
+
+    fn discover(
+        &self,
+        sequences: &[SequenceRecord],
+        config: &SearchConfig,
+    ) -> Result<MotifResult, Error> {
+        Ok(MotifResult::new(run_median_string(
+            &sequences_of(sequences),
+            config.k,
+            &config.options,
+        )?))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DiscriminativeMotifSearch {
+    /// sequences the input is compared against, which aren't masked between motifs
+    pub control: Vec<String>,
+}
+
+impl MotifAlgorithm for DiscriminativeMotifSearch {
+    fn name(&self) -> &str {
+        "Discriminative Motif Search"
+    }
+
+    fn discover(
+        &self,
+        sequences: &[SequenceRecord],
+        config: &SearchConfig,
+    ) -> Result<MotifResult, Error> {
+        let (motifs, enrichment) = run_discriminative_motif_search(
+            &sequences_of(sequences),
+            &self.control,
+            config.k,
+            &config.options,
+        )?;
+        Ok(MotifResult {
+            enrichment: Some(enrichment),
+            ..MotifResult::new(motifs)
+        })
+    }
+}
+
+/// Matches of a known pattern, which may hold IUPAC codes, within an edit distance
+#[derive(Debug, Clone)]
+pub struct PatternSearch {
+    pub pattern: String,
+    pub distance: u8,
+}
+
+impl MotifAlgorithm for PatternSearch {
+    fn name(&self) -> &str {
+        "Find Motif"
+    }
+
+    fn discover(
+        &self,
+        sequences: &[SequenceRecord],
+        _config: &SearchConfig,
+    ) -> Result<MotifResult, Error> {
+        let pattern = self.pattern.to_uppercase();
+        let hits = align_motifs_distance(sequences, &pattern, self.distance)?;
+        Ok(MotifResult {
+            hits: Some(hits),
+            ..MotifResult::new(vec![MotifInstance::new(pattern, Strand::Forward)])
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        run_algorithms, MedianString, MotifAlgorithm, MotifResult, PatternSearch,
+        RandomizedMotifSearch, SearchConfig,
+    };
+    use crate::sequence::{MotifInstance, SequenceRecord, SitePosition, Strand};
+    use crate::site_model::SiteModel;
+    use crate::SearchOptions;
+
+    #[test]
+    pub fn test_run_algorithms() {
+        let records = SequenceRecord::from_sequences(&[
+            "TTTTACGTACTTTTT",
+            "GGACGTACGGGGGG",
+            "CCCCCCCACGTACC",
+        ]);
+        let config = SearchConfig {
+            k: 6,
+            ..Default::default()
+        };
+        let algorithms: Vec<Box<dyn MotifAlgorithm>> = vec![
+            Box::new(MedianString),
+            Box::new(RandomizedMotifSearch { runs: 20 }),
+            Box::new(PatternSearch {
+                pattern: "acgtac".to_string(),
+                distance: 0,
+            }),
+        ];
+        assert_eq!(algorithms[1].name(), "Randomized Motif Search");
+        let results = run_algorithms(&algorithms, &records, &config).unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].motifs[0].kmer, "ACGTAC");
+        assert_eq!(results[1].motifs.len(), 3);
+        assert!(results[1].score_trace.is_some());
+        let hits = results[2].hits.as_ref().unwrap();
+        assert_eq!(hits.len(), 3);
+        assert_eq!(hits[2].id, "seq2");
+        assert!(PatternSearch {
+            pattern: "ACGXAC".to_string(),
+            distance: 0,
+        }
+        .discover(&records, &config)
+        .is_err());
+    }
+
+    #[test]
+    pub fn test_null_score_with_varying_sites() {
+        let records = SequenceRecord::from_sequences(&[
+            "CGCCTTTGACGCATCTGTGTCCA",
+            "GCTTACTTGACGCAACATGGTAC",
+            "ATTTGACGCAGCGGCCATTCAGA",
+            "CAGTCAGGCATTGACGCACCGTA",
+        ]);
+        let site = |sequence, offset| MotifInstance {
+            kmer: "TTGACGCA".to_string(),
+            strand: Strand::Forward,
+            position: Some(SitePosition { sequence, offset }),
+        };
+        let motif = MotifResult::new(vec![site(0, 5), site(1, 6), site(2, 1), site(3, 10)]);
+        let one_site = MotifResult::new(vec![MotifInstance {
+            kmer: "CAGTCAGG".to_string(),
+            ..site(3, 0)
+        }]);
+        for site_model in [SiteModel::Oops, SiteModel::Zoops, SiteModel::Tcm] {
+            let config = SearchConfig {
+                k: 8,
+                options: SearchOptions {
+                    site_model,
+                    ..Default::default()
+                },
+            };
+            let empty = MotifResult::new(vec![]);
+            assert_eq!(empty.null_score(&records, &config).unwrap(), f64::MAX);
+            let motif_score = motif.null_score(&records, &config).unwrap();
+            let one_site_score = one_site.null_score(&records, &config).unwrap();
+            // a lone site has no mismatches, so only the likelihood tells it apart from a real motif
+            match site_model {
+                SiteModel::Oops => assert!(one_site_score <= motif_score),
+                _ => assert!(motif_score < one_site_score),
+            }
+        }
+    }
+}
@@ -1,8 +1,12 @@
+use crate::iupac::iupac_nucleotides;
+use crate::sequence::SequenceRecord;
 use crate::Error;
 use bio::alignment::pairwise::Aligner;
 use bio::alignment::Alignment as BioAlignment;
-use bio::pattern_matching::myers::Myers;
+use bio::pattern_matching::myers::{Myers, MyersBuilder};
 use rayon::prelude::*;
+use serde::Serialize;
+use tracing::error;
 #[derive(PartialEq, Clone, Eq, Debug)]
 enum Pointer {
     Down,
@@ -70,9 +74,8 @@ pub fn local_alignment(
 fn max_of_matrix(matrix: &[Vec<isize>]) -> (usize, usize) {
     let mut max_so_far = isize::MIN;
     let (mut row, mut col) = (0, 0);
-    for i in 0..matrix.len() {
-        for j in 0..matrix[0].len() {
-            let curr = matrix[i][j];
+    for (i, matrix_row) in matrix.iter().enumerate() {
+        for (j, &curr) in matrix_row.iter().enumerate() {
             if curr > max_so_far {
                 max_so_far = curr;
                 row = i;
@@ -137,27 +140,72 @@ fn local_alignment_score_and_backtrack_matrix(
     Ok((alignment, row, col))
 }
 
-pub fn align_motifs_distance(sequences: &[String], consensus_string: &String, distance: u8) {
-    let mut seq_count = 0;
-    let mut count = 0;
-    for (i, sequence) in sequences.iter().enumerate() {
-        let pattern = consensus_string.as_bytes();
-        let sequence = sequence.as_bytes();
-        let mut myers = Myers::<u64>::new(pattern);
+fn iupac_myers(pattern: &[u8]) -> Myers<u64> {
+    // degenerate codes in the pattern match any of the nucleotides they stand for
+    let mut builder = MyersBuilder::new();
+    for code in b"RYSWKMBDHVN" {
+        if let Some(nucleotides) = iupac_nucleotides(*code as char) {
+            builder.ambig(*code, nucleotides.iter().map(|&nuc| nuc as u8));
+        }
+    }
+    builder.build_64(pattern)
+}
+
+fn iupac_matches(code: u8, nuc: u8) -> bool {
+    code == nuc
+        || iupac_nucleotides(code as char)
+            .is_some_and(|nucleotides| nucleotides.contains(&(nuc as char)))
+}
+
+/// An occurrence of a pattern in one of the sequences
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Hit {
+    /// index of the sequence in the input
+    pub sequence: usize,
+    /// identifier of the sequence from its FASTA header
+    pub id: String,
+    /// start and end (exclusive) of the match in the sequence
+    pub start: usize,
+    pub end: usize,
+    /// edit distance between the pattern and the match
+    pub distance: usize,
+    /// the matched part of the sequence
+    pub site: String,
+    /// alignment of the pattern against the match and up to two nucleotides either side, formatted for display
+    pub alignment: String,
+}
+
+/// Find every match of the pattern within the edit distance in the sequences
+///
+/// The pattern is made of IUPAC nucleotide codes and holds at most 64 of them.
+pub fn align_motifs_distance(
+    sequences: &[SequenceRecord],
+    pattern: &str,
+    distance: u8,
+) -> Result<Vec<Hit>, Error> {
+    if pattern
+        .chars()
+        .any(|code| iupac_nucleotides(code).is_none())
+    {
+        error!(
+            "{} contains characters that aren't IUPAC nucleotide codes",
+            pattern
+        );
+        return Err(Error::InvalidNucleotideError);
+    }
+    // the bit-parallel search keeps a column of the pattern in each bit of a u64
+    if pattern.is_empty() || pattern.len() > 64 {
+        return Err(Error::InvalidMotifLength);
+    }
+    let mut hits = vec![];
+    let pattern = pattern.as_bytes();
+    let mut myers = iupac_myers(pattern);
+    for (i, record) in sequences.iter().enumerate() {
+        let sequence = record.sequence.as_bytes();
         let mut aln = BioAlignment::default();
         let mut matches = myers.find_all(sequence, distance);
-        let mut seq = false;
         while matches.next_alignment(&mut aln) {
-            if !seq {
-                println!("Sequence: {}", i);
-                seq = true;
-                seq_count += 1;
-            }
             let sequence_len = sequence.len();
-            println!(
-                "Hit found in range: {}..{} (distance: {})",
-                aln.ystart, aln.yend, aln.score
-            );
             let y = if aln.ystart >= 2 {
                 if aln.yend + 2 < sequence_len {
                     &sequence[aln.ystart - 2..aln.yend + 2]
@@ -170,13 +218,45 @@ pub fn align_motifs_distance(sequences: &[String], consensus_string: &String, di
                 &sequence[aln.ystart..aln.yend]
             };
             let x = &pattern[aln.xstart..aln.xend];
-            let score = |a: u8, b: u8| if a == b { 1i32 } else { -1i32 };
+            let score = |a: u8, b: u8| if iupac_matches(a, b) { 1i32 } else { -1i32 };
             let mut aligner = Aligner::with_capacity(x.len(), y.len(), -5, -1, &score);
             let alignment = aligner.semiglobal(x, y);
-            println!("{}", alignment.pretty(x.as_ref(), y.as_ref(), 100));
-            count += 1;
+            hits.push(Hit {
+                sequence: i,
+                id: record.id.clone(),
+                start: aln.ystart,
+                end: aln.yend,
+                distance: aln.score as usize,
+                site: String::from_utf8_lossy(&sequence[aln.ystart..aln.yend]).to_string(),
+                alignment: alignment.pretty(x, y, 100),
+            });
         }
     }
-    println!("Number of matches: {}", count);
-    println!("Number of sequences with matches: {}", seq_count);
+    Ok(hits)
+}
+
+#[cfg(test)]
+mod test {
+    use super::align_motifs_distance;
+    use crate::sequence::SequenceRecord;
+
+    #[test]
+    pub fn test_align_motifs_distance() {
+        let sequences = SequenceRecord::from_sequences(&["GGTATAAAGG", "CCCCCC", "TATTTAT"]);
+        let hits = align_motifs_distance(&sequences, "TATAWAWR", 0).unwrap();
+        assert!(hits.is_empty());
+        let hits = align_motifs_distance(&sequences, "TATAWA", 0).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!((hits[0].sequence, hits[0].start, hits[0].end), (0, 2, 8));
+        assert_eq!(hits[0].site, "TATAAA");
+        assert_eq!(hits[0].id, "seq0");
+        assert_eq!(hits[0].distance, 0);
+        let hits = align_motifs_distance(&sequences, "TATAWA", 1).unwrap();
+        assert!(hits
+            .iter()
+            .any(|hit| hit.sequence == 2 && hit.distance == 1));
+        assert!(align_motifs_distance(&sequences, "TATAXA", 0).is_err());
+        assert!(align_motifs_distance(&sequences, "", 0).is_err());
+        assert!(align_motifs_distance(&sequences, &"A".repeat(65), 0).is_err());
+    }
 }
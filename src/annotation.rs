@@ -0,0 +1,305 @@
+use std::fmt::Display;
+use std::io::{self, Write};
+
+use clap::ValueEnum;
+
+use crate::alignment::Hit;
+use crate::motif_io::MotifFormat;
+use crate::scan::ScanHit;
+use crate::sequence::{MotifInstance, SequenceRecord, Strand};
+use serde::Serialize;
+
+/// Format of the output file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// the MotifFinder report
+    #[default]
+    #[value(name = "text")]
+    Text,
+    /// BED6 with one line per motif site
+    #[value(name = "bed")]
+    Bed,
+    /// GFF3 with one sequence_motif feature per motif site
+    #[value(name = "gff3")]
+    Gff3,
+    /// the MEME minimal format of the motifs, for Tomtom, FIMO and other MEME suite tools
+    #[value(name = "meme")]
+    Meme,
+    /// the parameters, timing, motifs, sites and hits of the run as JSON
+    #[value(name = "json")]
+    Json,
+    /// the count matrices of the motifs in JASPAR format
+    #[value(name = "jaspar")]
+    Jaspar,
+    /// the count matrices of the motifs in TRANSFAC format
+    #[value(name = "transfac")]
+    Transfac,
+    /// the probability matrices of the motifs in HOMER format
+    #[value(name = "homer")]
+    Homer,
+    /// a self-contained HTML report of the run with logos, site maps and score convergence
+    #[value(name = "html")]
+    Html,
+}
+
+impl OutputFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Text => "txt",
+            OutputFormat::Bed => "bed",
+            OutputFormat::Gff3 => "gff3",
+            OutputFormat::Meme => "meme",
+            OutputFormat::Json => "json",
+            OutputFormat::Jaspar => "jaspar",
+            OutputFormat::Transfac => "transfac",
+            OutputFormat::Homer => "motif",
+            OutputFormat::Html => "html",
+        }
+    }
+
+    /// The motif format for formats that hold matrices
+    pub fn motif_format(&self) -> Option<MotifFormat> {
+        match self {
+            OutputFormat::Jaspar => Some(MotifFormat::Jaspar),
+            OutputFormat::Transfac => Some(MotifFormat::Transfac),
+            OutputFormat::Homer => Some(MotifFormat::Homer),
+            _ => None,
+        }
+    }
+}
+
+impl Display for OutputFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OutputFormat::Text => write!(f, "text"),
+            OutputFormat::Bed => write!(f, "bed"),
+            OutputFormat::Gff3 => write!(f, "gff3"),
+            OutputFormat::Meme => write!(f, "meme"),
+            OutputFormat::Json => write!(f, "json"),
+            OutputFormat::Jaspar => write!(f, "jaspar"),
+            OutputFormat::Transfac => write!(f, "transfac"),
+            OutputFormat::Homer => write!(f, "homer"),
+            OutputFormat::Html => write!(f, "html"),
+        }
+    }
+}
+
+/// A motif site placed on the coordinates of its sequence
+#[derive(Debug, Clone, PartialEq)]
+pub struct Annotation {
+    /// FASTA id of the sequence
+    pub seqid: String,
+    /// 0-based start and exclusive end, shifted by the start of the sequence when its header has one
+    pub start: usize,
+    pub end: usize,
+    pub strand: Strand,
+    /// name of the motif the site belongs to
+    pub name: String,
+    pub site: String,
+    /// posterior of a discovered site, log-odds score of a scan hit or edit distance of a pattern match
+    pub score: Option<f64>,
+    pub p_value: Option<f64>,
+}
+
+/// 0-based offset of the sequence from a `[start-end]` coordinate in its FASTA description, e.g. `>AC_pNR [1979-2664]`
+pub fn genomic_offset(record: &SequenceRecord) -> Option<usize> {
+    let description = record.description.as_deref()?;
+    let open = description.find('[')?;
+    let close = open + description[open..].find(']')?;
+    let (start, end) = description[open + 1..close].split_once('-')?;
+    end.trim().parse::<usize>().ok()?;
+    start.trim().parse::<usize>().ok()?.checked_sub(1)
+}
+
+fn sequence_coordinates(records: &[SequenceRecord], index: usize) -> (String, usize) {
+    match records.get(index) {
+        Some(record) => (record.id.clone(), genomic_offset(record).unwrap_or(0)),
+        None => (index.to_string(), 0),
+    }
+}
+
+/// Annotations of the motif instances that were taken from the sequences, named after their motif
+pub fn instance_annotations(
+    motifs: &[MotifInstance],
+    name: &str,
+    records: &[SequenceRecord],
+    site_posteriors: Option<&[f64]>,
+) -> Vec<Annotation> {
+    motifs
+        .iter()
+        .enumerate()
+        .filter_map(|(i, motif)| {
+            let position = motif.position?;
+            let (seqid, offset) = sequence_coordinates(records, position.sequence);
+            let start = offset + position.offset;
+            Some(Annotation {
+                seqid,
+                start,
+                end: start + motif.kmer.len(),
+                strand: motif.strand,
+                name: name.to_string(),
+                site: motif.kmer.clone(),
+                score: site_posteriors.and_then(|posteriors| posteriors.get(i).copied()),
+                p_value: None,
+            })
+        })
+        .collect()
+}
+
+/// Annotations of the matches of a pattern, which are all on the forward strand
+pub fn hit_annotations(hits: &[Hit], pattern: &str, records: &[SequenceRecord]) -> Vec<Annotation> {
+    hits.iter()
+        .map(|hit| {
+            let (seqid, offset) = sequence_coordinates(records, hit.sequence);
+            Annotation {
+                seqid,
+                start: offset + hit.start,
+                end: offset + hit.end,
+                strand: Strand::Forward,
+                name: pattern.to_string(),
+                site: hit.site.clone(),
+                score: Some(hit.distance as f64),
+                p_value: None,
+            }
+        })
+        .collect()
+}
+
+pub fn scan_annotations(hits: &[ScanHit], records: &[SequenceRecord]) -> Vec<Annotation> {
+    hits.iter()
+        .map(|hit| {
+            let (seqid, offset) = sequence_coordinates(records, hit.sequence);
+            Annotation {
+                seqid,
+                start: offset + hit.start,
+                end: offset + hit.end,
+                strand: hit.strand,
+                name: hit.motif.clone(),
+                site: hit.site.clone(),
+                score: Some(hit.score),
+                p_value: Some(hit.p_value),
+            }
+        })
+        .collect()
+}
+
+/// Write the annotations as BED6
+///
+/// BED scores are integers from 0 to 1000, so the score column is -10 log10 of the p-value where there is one and 0 otherwise.
+pub fn write_bed<W: Write>(writer: &mut W, annotations: &[Annotation]) -> io::Result<()> {
+    for annotation in annotations {
+        let score = annotation.p_value.map_or(0.0, |p_value| {
+            (-10.0 * p_value.log10()).round().clamp(0.0, 1000.0)
+        });
+        writeln!(
+            writer,
+            "{}\t{}\t{}\t{}\t{}\t{}",
+            annotation.seqid,
+            annotation.start,
+            annotation.end,
+            // some readers split BED lines on any whitespace
+            annotation.name.replace(char::is_whitespace, "_"),
+            score,
+            annotation.strand
+        )?;
+    }
+    Ok(())
+}
+
+// characters with a special meaning in GFF3 columns and attributes
+fn escape_gff3(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            ';' | '=' | '&' | ',' | '%' | '\t' | '\n' | '\r' => {
+                escaped.push_str(&format!("%{:02X}", c as u32))
+            }
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Write the annotations as GFF3 sequence_motif features with 1-based inclusive coordinates
+pub fn write_gff3<W: Write>(writer: &mut W, annotations: &[Annotation]) -> io::Result<()> {
+    writeln!(writer, "##gff-version 3")?;
+    for (i, annotation) in annotations.iter().enumerate() {
+        let score = annotation
+            .score
+            .map_or_else(|| ".".to_string(), |score| format!("{score:.3}"));
+        let mut attributes = format!(
+            "ID=site{};Name={};site={}",
+            i + 1,
+            escape_gff3(&annotation.name),
+            annotation.site
+        );
+        if let Some(p_value) = annotation.p_value {
+            attributes.push_str(&format!(";p_value={p_value:.3e}"));
+        }
+        writeln!(
+            writer,
+            "{}\tMotifFinder\tsequence_motif\t{}\t{}\t{}\t{}\t.\t{}",
+            escape_gff3(&annotation.seqid),
+            annotation.start + 1,
+            annotation.end,
+            score,
+            annotation.strand,
+            attributes
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::sequence::{MotifInstance, SequenceRecord, SitePosition, Strand};
+
+    use super::{genomic_offset, instance_annotations, write_bed, write_gff3};
+
+    #[test]
+    pub fn test_annotations() {
+        let mut records = SequenceRecord::from_sequences(&["ACGTACGTAC", "TTGACATTGA"]);
+        records[0].id = "AC_pNR".to_string();
+        records[0].description = Some("[1979-2664]".to_string());
+        assert_eq!(genomic_offset(&records[0]), Some(1978));
+        assert_eq!(genomic_offset(&records[1]), None);
+        let motifs = vec![
+            MotifInstance {
+                kmer: "GTAC".to_string(),
+                strand: Strand::Forward,
+                position: Some(SitePosition {
+                    sequence: 0,
+                    offset: 2,
+                }),
+            },
+            MotifInstance {
+                kmer: "TGTC".to_string(),
+                strand: Strand::Reverse,
+                position: Some(SitePosition {
+                    sequence: 1,
+                    offset: 2,
+                }),
+            },
+            // motifs without a position, like a median string, aren't sites
+            MotifInstance::new("GTAC".to_string(), Strand::Forward),
+        ];
+        let annotations = instance_annotations(&motifs, "GTAC", &records, None);
+        assert_eq!(annotations.len(), 2);
+        let mut bed = vec![];
+        write_bed(&mut bed, &annotations).unwrap();
+        assert_eq!(
+            String::from_utf8(bed).unwrap(),
+            "AC_pNR\t1980\t1984\tGTAC\t0\t+\nseq1\t2\t6\tGTAC\t0\t-\n"
+        );
+        let mut gff3 = vec![];
+        write_gff3(&mut gff3, &annotations).unwrap();
+        let gff3 = String::from_utf8(gff3).unwrap();
+        let lines: Vec<&str> = gff3.lines().collect();
+        assert_eq!(lines[0], "##gff-version 3");
+        assert_eq!(
+            lines[1],
+            "AC_pNR\tMotifFinder\tsequence_motif\t1981\t1984\t.\t+\t.\tID=site1;Name=GTAC;site=GTAC"
+        );
+    }
+}
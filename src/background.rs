@@ -0,0 +1,139 @@
+use tracing::trace;
+
+fn nucleotide_index(nuc: char) -> Option<usize> {
+    match nuc {
+        'A' => Some(0),
+        'C' => Some(1),
+        'G' => Some(2),
+        'T' => Some(3),
+        _ => None,
+    }
+}
+
+/// Markov model of the nucleotides expected outside of motif sites
+///
+/// An order n model gives the probability of each nucleotide given the n nucleotides before it.
+/// The models of every lower order are kept as well so the first nucleotides of a kmer, which have
+/// a shorter context, can still be scored.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Background {
+    order: usize,
+    // probabilities[o][context] are the ACGT probabilities following the o nucleotides encoded by context
+    probabilities: Vec<Vec<[f64; 4]>>,
+}
+
+impl Default for Background {
+    fn default() -> Self {
+        Background::uniform()
+    }
+}
+
+impl Background {
+    /// Equal probability for every nucleotide
+    pub fn uniform() -> Background {
+        Background {
+            order: 0,
+            probabilities: vec![vec![[0.25; 4]]],
+        }
+    }
+
+    /// Estimate an order `order` model from the given sequences, with a pseudocount of 1 per entry
+    #[tracing::instrument(skip(sequences))]
+    pub fn from_sequences(sequences: &[String], order: usize) -> Background {
+        let mut counts: Vec<Vec<[f64; 4]>> = (0..=order)
+            .map(|o| vec![[1.0; 4]; 4usize.pow(o as u32)])
+            .collect();
+        for seq in sequences {
+            let indices: Vec<Option<usize>> = seq.chars().map(nucleotide_index).collect();
+            for (i, nuc) in indices.iter().enumerate() {
+                let Some(nuc) = nuc else {
+                    continue;
+                };
+                for (o, table) in counts.iter_mut().enumerate() {
+                    if o > i {
+                        break;
+                    }
+                    if let Some(context) = encode_context(&indices[i - o..i]) {
+                        table[context][*nuc] += 1.0;
+                    }
+                }
+            }
+        }
+        for table in counts.iter_mut() {
+            for row in table.iter_mut() {
+                let sum: f64 = row.iter().sum();
+                for p in row.iter_mut() {
+                    *p /= sum;
+                }
+            }
+        }
+        trace!(?counts);
+        Background {
+            order,
+            probabilities: counts,
+        }
+    }
+
+    pub fn order(&self) -> usize {
+        self.order
+    }
+
+    /// Zero order ACGT frequencies
+    pub fn frequencies(&self) -> [f64; 4] {
+        self.probabilities[0][0]
+    }
+
+    /// Probability of a kmer under the background, using as much of the preceding kmer as context as the order allows
+    pub fn probability(&self, kmer: &str) -> f64 {
+        let mut probability = 1.0;
+        let mut context: Vec<usize> = Vec::with_capacity(self.order);
+        for nuc in kmer.chars() {
+            let Some(nuc) = nucleotide_index(nuc) else {
+                // unknown nucleotides don't contribute, the same as in generate_probability
+                context.clear();
+                continue;
+            };
+            let o = context.len();
+            let index = context.iter().fold(0, |index, n| index * 4 + n);
+            probability *= self.probabilities[o][index][nuc];
+            if self.order > 0 {
+                if context.len() == self.order {
+                    context.remove(0);
+                }
+                context.push(nuc);
+            }
+        }
+        probability
+    }
+}
+
+fn encode_context(context: &[Option<usize>]) -> Option<usize> {
+    context
+        .iter()
+        .try_fold(0, |index, nuc| nuc.map(|nuc| index * 4 + nuc))
+}
+
+#[cfg(test)]
+mod test {
+    use super::Background;
+
+    #[test]
+    pub fn test_zero_order() {
+        let background = Background::from_sequences(&["AAAAAACCGT".to_string()], 0);
+        let frequencies = background.frequencies();
+        // counts of 6, 2, 1, 1 plus a pseudocount each
+        assert!((frequencies[0] - 0.5).abs() < 1e-9);
+        assert!((frequencies[1] - 3.0 / 14.0).abs() < 1e-9);
+        assert!((background.probability("AC") - 0.5 * 3.0 / 14.0).abs() < 1e-9);
+        assert!((Background::uniform().probability("ACGT") - 0.25f64.powi(4)).abs() < 1e-12);
+    }
+
+    #[test]
+    pub fn test_markov() {
+        let background = Background::from_sequences(&["ACACACACACACACACACAC".to_string()], 1);
+        assert_eq!(background.order(), 1);
+        // an A is always followed by a C in the input
+        assert!(background.probability("AC") > background.probability("AA"));
+        assert!(background.probability("CA") > background.probability("CC"));
+    }
+}
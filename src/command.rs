@@ -1,21 +1,52 @@
-use std::ops::RangeInclusive;
+use std::{
+    fmt::Display,
+    fs::{self, File},
+    ops::RangeInclusive,
+    sync::Arc,
+};
 
 use crate::{
+    algorithm::{
+        run_algorithms, DiscriminativeMotifSearch, ExpectationMaximization, GibbsSampler,
+        MedianString, MotifAlgorithm, MotifResult, PatternSearch, RandomizedMotifSearch,
+        SearchConfig,
+    },
     align_motifs_multi_threaded,
-    alignment::align_motifs_distance,
-    generate_consensus_string, load_data, run_gibbs_sampler, run_median_string,
-    run_randomized_motif_search, unique_motifs,
+    alignment::{align_motifs_distance, Hit},
+    annotation::{scan_annotations, OutputFormat},
+    background::Background,
+    discriminative::EnrichedKmer,
+    ensemble::{ensemble_motifs, EnsembleMember, EnsembleMotif},
+    generate_consensus_string,
+    iupac::{iupac_consensus, IupacThresholds},
+    json::JsonResults,
+    load_data, load_records,
+    logo::render_logo,
+    mask_motif_sites,
+    motif_io::read_matrices,
+    motif_io::MotifFormat,
+    progress::ProgressBars,
+    run_rng, run_scan,
+    scan::{ScanHit, ScanThreshold},
+    scoring::ScoringMethod,
+    sequence::{sequences_of, MotifInstance, SequenceRecord},
+    shuffle::{shuffle_sequences, Significance},
+    site_model::SiteModel,
+    unique_motifs,
     utils::{
-        create_output_file, generate_vector_space_delimited, output_results_to_file,
-        write_file_header,
+        create_output_file, format_hits, generate_vector_space_delimited,
+        output_annotations_to_file, output_html_report_to_file, output_matrices_to_file,
+        output_meme_to_file, output_results_to_file, output_scan_results_to_file,
+        results_annotations, results_matrices, results_meme_motifs, write_file_header,
     },
-    Error,
+    Error, SearchOptions,
 };
-use chrono::Utc;
-use clap::{Args, Parser, Subcommand};
+use chrono::{DateTime, Utc};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use clap_verbosity_flag::InfoLevel;
 use rayon::prelude::*;
-use tracing::{error, info, trace};
+use serde::Serialize;
+use tracing::{error, info, trace, warn};
 /// Motif Finder
 #[derive(Debug, Parser)]
 #[command(author, version, about, long_about = None)]
@@ -33,100 +64,581 @@ impl MotifFinder {
         let dt = Utc::now();
         let start_time: i64 = dt.timestamp_micros();
         println!("Welcome to MotifFinder!");
-        let sequences = load_data(&self.global_opts.input_file, self.global_opts.num_entries)?;
-        self.global_opts.num_entries = sequences.len();
-        let GlobalOpts { k, .. } = self.global_opts;
+        if let Commands::Logo {
+            matrix,
+            matrix_format,
+            prefix,
+        } = &self.command
+        {
+            // drawing saved motifs doesn't need the sequences
+            let logos: Vec<(String, Vec<Vec<f64>>)> = read_matrices(matrix, *matrix_format)?
+                .into_iter()
+                .map(|matrix| {
+                    let profile = matrix.frequencies();
+                    (matrix.name, profile)
+                })
+                .collect();
+            return write_logos(prefix, &logos);
+        }
+        let (Some(input_file), Some(num_entries)) =
+            (&self.global_opts.input_file, self.global_opts.num_entries)
+        else {
+            error!("An input file and the number of entries to read (-e) are required by every command but logo");
+            return Err(Error::InvalidInputError);
+        };
+        let records = load_records(input_file, num_entries)?;
+        self.global_opts.num_entries = Some(records.len());
+        let sequences = sequences_of(&records);
+        let GlobalOpts {
+            k,
+            both_strands,
+            site_model,
+            scoring,
+            background_order,
+            output_format,
+            seed,
+            ..
+        } = self.global_opts;
+        let site_format = matches!(
+            output_format,
+            OutputFormat::Text | OutputFormat::Json | OutputFormat::Bed | OutputFormat::Gff3
+        );
+        if let Commands::Scan { .. } = self.command {
+            if !site_format {
+                error!(
+                    "Scan hits can be saved as text, json, bed or gff3 but not {}",
+                    output_format
+                );
+                return Err(Error::InvalidInputError);
+            }
+            // a scan looks for known matrices once, scored against the zero order composition
+            if self.global_opts.num_motifs != 1
+                || self.global_opts.shuffles > 0
+                || background_order > 0
+            {
+                error!("scan doesn't take --num-motifs, --shuffles or --background-order");
+                return Err(Error::InvalidInputError);
+            }
+        }
+        let background = match &self.global_opts.background_file {
+            Some(background_file) => {
+                let background_sequences = load_data(background_file, usize::MAX)?;
+                Background::from_sequences(&background_sequences, background_order)
+            }
+            None => Background::from_sequences(&sequences, background_order),
+        };
+        let options = SearchOptions {
+            both_strands,
+            site_model,
+            scoring,
+            background,
+            seed,
+            progress: Some(Arc::new(ProgressBars::default())),
+        };
 
         let (file, file_path) = if let Some(save_flag) = &self.global_opts.output_file {
-            let (mut file, file_path) = create_output_file(save_flag, k, start_time)?;
-            match write_file_header(
-                &mut file,
-                self.global_opts.k,
-                self.global_opts.num_entries,
-                &self.command,
-                dt,
-            ) {
-                Ok(()) => {
-                    trace!("Wrote file header to {}", file_path);
+            let (mut file, file_path) =
+                create_output_file(save_flag, k, start_time, output_format)?;
+            // the other formats carry their own layout, or the parameters in the case of JSON
+            if output_format == OutputFormat::Text {
+                match write_file_header(&mut file, &self.global_opts, &options, &self.command, dt) {
+                    Ok(()) => {
+                        trace!("Wrote file header to {}", file_path);
+                    }
+                    Err(_err) => return Err(Error::IOError),
                 }
-                Err(_err) => return Err(Error::IOError),
             }
             (Some(file), Some(file_path))
         } else {
             (None, None)
         };
         let command_clone = (self.command).clone();
-        let motifs = match self.command {
-            Commands::GibbsSampler {
-                num_iterations,
-                num_runs,
-            } => run_gibbs_sampler(&sequences, k, num_runs, num_iterations),
-            Commands::MedianString => run_median_string(&sequences, k),
-            Commands::Randomized { num_runs } => {
-                run_randomized_motif_search(&sequences, k, num_runs)
-            }
-            Commands::FindMotif { motif, distance } => {
-                align_motifs_distance(&sequences, &motif, distance);
-                Ok(vec![motif])
-            }
-        }?;
-        let unique_motifs: Vec<String> = unique_motifs(&motifs).into_par_iter().collect();
-        let unique_motifs_string = generate_vector_space_delimited(&unique_motifs);
-        println!("Unique motifs: {}", unique_motifs_string);
-        let consensus_string = generate_consensus_string(&motifs, k)?;
-        println!("Consensus string: {}", consensus_string);
-
-        let (best_motif_score, best_motif) = if self.global_opts.align {
-            let top_five = align_motifs_multi_threaded(&sequences, &unique_motifs)?;
-            println!("Top 5 motifs:");
-            for (score, motif) in &top_five {
-                println!("{}: {}", score, motif);
-            }
-            let (best_motif_score, best_motif) = top_five[0].clone();
-            align_motifs_distance(&sequences, &consensus_string, 1);
-            (Some(best_motif_score), Some(best_motif))
-        } else {
-            (None, None)
+        let dt_end = match &self.command {
+            Commands::Scan {
+                matrix,
+                matrix_format,
+                min_score,
+                max_p_value,
+            } => {
+                let threshold = match (min_score, max_p_value) {
+                    (Some(min_score), _) => ScanThreshold::Score(*min_score),
+                    (None, max_p_value) => {
+                        ScanThreshold::PValue(max_p_value.unwrap_or(DEFAULT_MAX_P_VALUE))
+                    }
+                };
+                let hits = run_scan(&records, matrix, *matrix_format, threshold, &options)?;
+                print_scan_hits(&hits);
+                save_results(file, file_path, |file| match output_format {
+                    OutputFormat::Text => output_scan_results_to_file(file, &hits),
+                    OutputFormat::Json => {
+                        let dt_end = Utc::now();
+                        JsonResults::new(
+                            &self.global_opts,
+                            &self.command,
+                            options.background.frequencies(),
+                            dt,
+                            dt_end,
+                        )
+                        .with_scan_hits(&hits)
+                        .write(file)
+                        .map_err(|_| Error::IOError)?;
+                        Ok(dt_end)
+                    }
+                    format => {
+                        output_annotations_to_file(file, &scan_annotations(&hits, &records), format)
+                    }
+                })?
+            }
+            _ => {
+                let results = match self.command {
+                    Commands::Ensemble { .. } => self.find_ensemble_motifs(&records, &options)?,
+                    _ => self.find_motifs(&records, &options)?,
+                };
+                if let Some(logo_flag) = &self.global_opts.logo {
+                    let prefix = logo_flag
+                        .clone()
+                        .unwrap_or_else(|| format!("MotifFinder-logo-{start_time}"));
+                    let logos: Vec<(String, Vec<Vec<f64>>)> =
+                        results_meme_motifs(&results, &self.command)?
+                            .into_iter()
+                            .map(|motif| (motif.name, motif.profile))
+                            .collect();
+                    write_logos(&prefix, &logos)?;
+                }
+                save_results(file, file_path, |file| match output_format {
+                    OutputFormat::Text => {
+                        output_results_to_file(file, &results, &records, command_clone)
+                    }
+                    OutputFormat::Json => {
+                        let dt_end = Utc::now();
+                        JsonResults::new(
+                            &self.global_opts,
+                            &self.command,
+                            options.background.frequencies(),
+                            dt,
+                            dt_end,
+                        )
+                        .with_motifs(&results, &records)?
+                        .write(file)
+                        .map_err(|_| Error::IOError)?;
+                        Ok(dt_end)
+                    }
+                    OutputFormat::Html => output_html_report_to_file(
+                        file,
+                        &self.global_opts,
+                        &options,
+                        &results,
+                        &records,
+                        &command_clone,
+                        dt,
+                    ),
+                    OutputFormat::Meme => output_meme_to_file(
+                        file,
+                        &results_meme_motifs(&results, &command_clone)?,
+                        options.background.frequencies(),
+                        both_strands,
+                    ),
+                    format => match format.motif_format() {
+                        Some(motif_format) => output_matrices_to_file(
+                            file,
+                            &results_matrices(&results)?,
+                            motif_format,
+                        ),
+                        None => output_annotations_to_file(
+                            file,
+                            &results_annotations(&results, &records, &command_clone),
+                            format,
+                        ),
+                    },
+                })?
+            }
+        };
+
+        if let Some(duration) = dt_end.signed_duration_since(dt).num_microseconds() {
+            info!("Done in {} seconds", duration as f64 / 1_000_000.0);
+        }
+        Ok(())
+    }
+
+    /// Search for up to `num_motifs` motifs, masking the sites of each before looking for the next
+    fn find_motifs(
+        &self,
+        records: &[SequenceRecord],
+        options: &SearchOptions,
+    ) -> Result<Vec<(Vec<MotifInstance>, Summary)>, Error> {
+        let GlobalOpts { k, scoring, .. } = self.global_opts;
+        let algorithm = self.command.algorithm()?;
+        let config = SearchConfig {
+            k,
+            options: options.clone(),
         };
-        let dt_end = if let Some(mut file) = file {
+        let num_motifs = self.global_opts.num_motifs;
+        let shuffles = self.global_opts.shuffles;
+        if num_motifs == 0 {
+            return Err(Error::InvalidNumberMotifs);
+        }
+        // a known pattern has one set of matches, and nothing to compare them against in shuffled sequences
+        if let Commands::FindMotif { .. } = self.command {
+            if num_motifs != 1 || shuffles > 0 {
+                error!("find_motif doesn't take --num-motifs or --shuffles");
+                return Err(Error::InvalidInputError);
+            }
+        }
+        let sequences = sequences_of(records);
+        let mut masked_records = records.to_vec();
+        let mut results = vec![];
+        for motif_index in 0..num_motifs {
+            if num_motifs > 1 {
+                println!("Motif {} of {}", motif_index + 1, num_motifs);
+            }
+            let discovery = match algorithm.discover(&masked_records, &config) {
+                Ok(discovered) => discovered,
+                Err(Error::NoMotifsFound) if motif_index > 0 => {
+                    warn!(
+                        "No unmasked sites left, stopping after {} motifs",
+                        motif_index
+                    );
+                    break;
+                }
+                Err(err) => return Err(err),
+            };
+            let significance = match shuffles {
+                0 => None,
+                _ => {
+                    // run the same search on shuffled copies of the input to see how often chance does as well
+                    let observed = discovery.null_score(&masked_records, &config)?;
+                    // the shuffles draw from a generator of their own rather than one of the runs of the search
+                    let mut shuffle_rng = run_rng(options.seed.map(|seed| !seed), motif_index);
+                    let null_scores = (1..=shuffles)
+                        .map(|i| {
+                            info!("Searching shuffled sequences {} of {}", i, shuffles);
+                            let shuffled = replace_sequences(
+                                &masked_records,
+                                shuffle_sequences(&sequences_of(&masked_records), &mut shuffle_rng),
+                            );
+                            match algorithm.discover(&shuffled, &config) {
+                                Ok(null) => null.null_score(&shuffled, &config),
+                                Err(Error::NoMotifsFound) => Ok(f64::MAX),
+                                Err(err) => Err(err),
+                            }
+                        })
+                        .collect::<Result<Vec<f64>, Error>>()?;
+                    Some(Significance::from_null(observed, &null_scores))
+                }
+            };
+            let MotifResult {
+                motifs,
+                site_posteriors,
+                log_likelihood,
+                enrichment,
+                hits,
+                score_trace,
+            } = discovery;
+            if let Some(log_likelihood) = log_likelihood {
+                println!("Log likelihood: {:.3}", log_likelihood);
+            }
+            if let Some(hits) = &hits {
+                print!("{}", format_hits(hits));
+            }
+            let mut unique_motifs: Vec<String> = unique_motifs(&motifs).into_par_iter().collect();
+            // in a fixed order, so a seeded run reports the same thing every time
+            unique_motifs.sort();
+            let unique_motifs_string = generate_vector_space_delimited(&unique_motifs);
+            println!("Unique motifs: {}", unique_motifs_string);
+            let consensus_string = generate_consensus_string(&motifs, k)?;
+            println!("Consensus string: {}", consensus_string);
+            let thresholds = IupacThresholds {
+                single: self.global_opts.iupac_single,
+                pair: self.global_opts.iupac_pair,
+            };
+            let degenerate_consensus = iupac_consensus(&motifs, &thresholds)?;
+            println!("Degenerate consensus (IUPAC): {}", degenerate_consensus);
+            let motif_score = scoring.score(&motifs, &options.background);
+            println!("Score ({}): {:.3}", scoring, motif_score);
+            if let Some(significance) = &significance {
+                println!(
+                    "Empirical p-value: {:.3e} ({} shuffles)",
+                    significance.p_value, significance.shuffles
+                );
+            }
+
+            let (best_motif_score, best_motif, top_motifs, hits) = if self.global_opts.align {
+                let top_five =
+                    align_motifs_multi_threaded(&sequences, &unique_motifs, options.progress())?;
+                println!("Top 5 motifs:");
+                for (score, motif) in &top_five {
+                    println!("{}: {}", score, motif);
+                }
+                let (best_motif_score, best_motif) = top_five[0].clone();
+                let alignment_hits = align_motifs_distance(records, &consensus_string, 1)?;
+                print!("{}", format_hits(&alignment_hits));
+                (
+                    Some(best_motif_score),
+                    Some(best_motif),
+                    Some(top_five),
+                    hits.or(Some(alignment_hits)),
+                )
+            } else {
+                (None, None, None, hits)
+            };
             let summary = Summary {
                 consensus_string,
+                degenerate_consensus,
                 best_motif,
                 best_motif_score,
+                top_motifs,
                 unique_motifs: unique_motifs_string,
+                site_posteriors,
+                motif_score: Some((scoring, motif_score)),
+                enrichment,
+                significance,
+                hits,
+                score_trace,
+                ensemble: None,
             };
-            match output_results_to_file(&mut file, &motifs, &summary, command_clone) {
-                Ok(dt_end) => {
-                    println!("Results saved to {}", file_path.ok_or(Error::IOError)?);
-                    dt_end
-                }
-                Err(_err) => {
-                    error!(
-                        "Error writing to file: {}",
-                        file_path.ok_or(Error::IOError)?
-                    );
-                    return Err(Error::IOError);
-                }
+            if let Commands::FindMotif { .. } = self.command {
+                results.push((motifs, summary));
+                break;
             }
-        } else {
-            Utc::now()
+            // hide the sites of this motif so the next search finds a different one
+            let next_records = replace_sequences(
+                &masked_records,
+                mask_motif_sites(&sequences_of(&masked_records), &motifs),
+            );
+            results.push((motifs, summary));
+            if motif_index + 1 < num_motifs && next_records == masked_records {
+                warn!(
+                    "No occurrences of motif {} to mask, stopping",
+                    motif_index + 1
+                );
+                break;
+            }
+            masked_records = next_records;
+        }
+        // correct for the motifs actually found, as the search can stop before num_motifs
+        let found = results.len();
+        for (i, (_, summary)) in results.iter_mut().enumerate() {
+            if let Some(significance) = &mut summary.significance {
+                significance.adjust(found);
+                println!(
+                    "Motif {} adjusted p-value: {:.3e} ({} motifs found)",
+                    i + 1,
+                    significance.adjusted_p_value,
+                    found
+                );
+            }
+        }
+        Ok(results)
+    }
+
+    /// Run every algorithm of the ensemble command once and keep the motifs enough of them agree on
+    fn find_ensemble_motifs(
+        &self,
+        records: &[SequenceRecord],
+        options: &SearchOptions,
+    ) -> Result<Vec<(Vec<MotifInstance>, Summary)>, Error> {
+        let Commands::Ensemble {
+            algorithms,
+            num_runs,
+            num_iterations,
+            max_distance,
+            min_support,
+        } = &self.command
+        else {
+            return Err(Error::InvalidInputError);
+        };
+        // the ensemble already reports every motif the algorithms agree on, and its support isn't a score to shuffle
+        if self.global_opts.num_motifs != 1 || self.global_opts.shuffles > 0 {
+            error!("ensemble doesn't take --num-motifs or --shuffles");
+            return Err(Error::InvalidInputError);
+        }
+        let mut chosen: Vec<EnsembleAlgorithm> = vec![];
+        for algorithm in algorithms {
+            if !chosen.contains(algorithm) {
+                chosen.push(*algorithm);
+            }
+        }
+        let algorithms: Vec<Box<dyn MotifAlgorithm>> = chosen
+            .iter()
+            .map(|algorithm| algorithm.algorithm(*num_runs, *num_iterations))
+            .collect();
+        let GlobalOpts { k, scoring, .. } = self.global_opts;
+        let config = SearchConfig {
+            k,
+            options: options.clone(),
+        };
+        let discovered = run_algorithms(&algorithms, records, &config)?;
+        let ensemble: Vec<EnsembleMotif> =
+            ensemble_motifs(&algorithms, &discovered, &config, *max_distance)?
+                .into_iter()
+                .filter(|motif| motif.support() >= *min_support)
+                .collect();
+        if ensemble.is_empty() {
+            warn!("No motif was found by at least {} algorithms", min_support);
+        }
+        let thresholds = IupacThresholds {
+            single: self.global_opts.iupac_single,
+            pair: self.global_opts.iupac_pair,
         };
+        let mut results = vec![];
+        for (i, motif) in ensemble.iter().enumerate() {
+            println!(
+                "Ensemble motif {} of {}, found by {} of {} algorithms",
+                i + 1,
+                ensemble.len(),
+                motif.support(),
+                algorithms.len()
+            );
+            for member in &motif.members {
+                let strand = if member.reverse_complement {
+                    " (reverse complement)"
+                } else {
+                    ""
+                };
+                println!(
+                    "{}: {}{}, score ({}): {:.3}",
+                    member.algorithm, member.consensus, strand, scoring, member.score
+                );
+            }
+            let sites = motif.sites();
+            let mut unique_motifs: Vec<String> = unique_motifs(&sites).into_iter().collect();
+            unique_motifs.sort();
+            let unique_motifs_string = generate_vector_space_delimited(&unique_motifs);
+            println!("Unique motifs: {}", unique_motifs_string);
+            let consensus_string = generate_consensus_string(&sites, k)?;
+            println!("Consensus string: {}", consensus_string);
+            let degenerate_consensus = iupac_consensus(&sites, &thresholds)?;
+            println!("Degenerate consensus (IUPAC): {}", degenerate_consensus);
+            let motif_score = scoring.score(&sites, &options.background);
+            println!("Score ({}): {:.3}", scoring, motif_score);
+            let summary = Summary {
+                consensus_string,
+                degenerate_consensus,
+                unique_motifs: unique_motifs_string,
+                best_motif: None,
+                best_motif_score: None,
+                top_motifs: None,
+                site_posteriors: None,
+                motif_score: Some((scoring, motif_score)),
+                enrichment: None,
+                significance: None,
+                hits: None,
+                score_trace: None,
+                ensemble: Some(motif.members.clone()),
+            };
+            results.push((sites, summary));
+        }
+        Ok(results)
+    }
+}
 
-        if let Some(duration) = dt_end.signed_duration_since(dt).num_microseconds() {
-            info!("Done in {} seconds", duration as f64 / 1_000_000.0);
+const DEFAULT_MAX_P_VALUE: f64 = 1e-4;
+
+/// The records with their sequences swapped for the given ones, such as masked or shuffled copies
+fn replace_sequences(records: &[SequenceRecord], sequences: Vec<String>) -> Vec<SequenceRecord> {
+    records
+        .iter()
+        .zip(sequences)
+        .map(|(record, sequence)| SequenceRecord {
+            sequence,
+            ..record.clone()
+        })
+        .collect()
+}
+
+fn save_results(
+    file: Option<File>,
+    file_path: Option<String>,
+    write: impl FnOnce(&mut File) -> Result<DateTime<Utc>, Error>,
+) -> Result<DateTime<Utc>, Error> {
+    let Some(mut file) = file else {
+        return Ok(Utc::now());
+    };
+    match write(&mut file) {
+        Ok(dt_end) => {
+            println!("Results saved to {}", file_path.ok_or(Error::IOError)?);
+            Ok(dt_end)
+        }
+        Err(_err) => {
+            error!(
+                "Error writing to file: {}",
+                file_path.ok_or(Error::IOError)?
+            );
+            Err(Error::IOError)
         }
-        Ok(())
     }
 }
 
-#[derive(Debug, Args)]
-struct GlobalOpts {
-    /// file path of the genome
-    input_file: String,
+fn write_logos(prefix: &str, logos: &[(String, Vec<Vec<f64>>)]) -> Result<(), Error> {
+    for (i, (name, profile)) in logos.iter().enumerate() {
+        let path = format!("{}-{}.svg", prefix, i + 1);
+        fs::write(&path, render_logo(name, profile)).map_err(|_| {
+            error!("Error writing logo to {}", path);
+            Error::IOError
+        })?;
+        println!("Logo of {} saved to {}", name, path);
+    }
+    Ok(())
+}
 
-    /// how many entries to read
+fn print_scan_hits(hits: &[ScanHit]) {
+    println!("motif\tsequence\tstart\tend\tstrand\tscore\tp-value\tsite");
+    for hit in hits {
+        println!(
+            "{}\t{}\t{}\t{}\t{}\t{:.3}\t{:.3e}\t{}",
+            hit.motif, hit.id, hit.start, hit.end, hit.strand, hit.score, hit.p_value, hit.site
+        );
+    }
+    println!("Number of sites: {}", hits.len());
+}
+
+impl Commands {
+    /// The algorithm the command runs, or an error for commands that don't discover motifs
+    fn algorithm(&self) -> Result<Box<dyn MotifAlgorithm>, Error> {
+        Ok(match self {
+            Commands::GibbsSampler {
+                num_iterations,
+                num_runs,
+            } => Box::new(GibbsSampler {
+                runs: *num_runs,
+                iterations: *num_iterations,
+            }),
+            Commands::ExpectationMaximization {
+                num_iterations,
+                num_runs,
+            } => Box::new(ExpectationMaximization {
+                runs: *num_runs,
+                iterations: *num_iterations,
+            }),
+            Commands::MedianString => Box::new(MedianString),
+            Commands::Randomized { num_runs } => {
+                Box::new(RandomizedMotifSearch { runs: *num_runs })
+            }
+            // the control set is loaded once and isn't masked between motifs
+            Commands::Discriminative { control } => Box::new(DiscriminativeMotifSearch {
+                control: load_data(control, usize::MAX)?,
+            }),
+            Commands::FindMotif { motif, distance } => Box::new(PatternSearch {
+                pattern: motif.clone(),
+                distance: *distance,
+            }),
+            // scanning looks for sites of a known matrix and logos draw saved motifs rather than discovering motifs
+            // the ensemble runs several algorithms of its own
+            Commands::Scan { .. } | Commands::Logo { .. } | Commands::Ensemble { .. } => {
+                return Err(Error::InvalidInputError)
+            }
+        })
+    }
+}
+
+#[derive(Debug, Args, Serialize)]
+pub(crate) struct GlobalOpts {
+    /// file path of the genome (required by every command but logo)
+    input_file: Option<String>,
+
+    /// how many entries to read (required by every command but logo)
     #[arg(short = 'e', long = "entries")]
-    pub num_entries: usize,
+    pub num_entries: Option<usize>,
 
     /// motif length
     #[arg(short,value_parser=k_in_range,default_value_t=8)]
@@ -136,9 +648,57 @@ struct GlobalOpts {
     #[arg(short = 'a', long = "align")]
     align: bool,
 
+    /// search the reverse complement strand as well
+    #[arg(long = "both-strands")]
+    both_strands: bool,
+
+    /// number of motif sites per sequence (used by gibbs and randomized)
+    #[arg(long = "site-model", value_enum, default_value_t = SiteModel::Oops)]
+    site_model: SiteModel,
+
+    /// score used to rank motif sets and reported with the results (only hamming with --site-model zoops or tcm)
+    #[arg(long = "scoring", value_enum, default_value_t = ScoringMethod::Hamming)]
+    scoring: ScoringMethod,
+
+    /// number of distinct motifs to find, masking the sites of each motif before searching for the next (not for ensemble)
+    #[arg(short = 'n', long = "num-motifs", default_value_t = 1)]
+    pub num_motifs: usize,
+
+    /// number of dinucleotide shuffles of the input to search for an empirical p-value and adjusted p-value of each motif (not for ensemble)
+    #[arg(long = "shuffles", default_value_t = 0)]
+    pub shuffles: usize,
+
+    /// minimum frequency of a nucleotide written on its own in the IUPAC consensus (it must also be twice as common as the next)
+    #[arg(long = "iupac-single", value_parser = fraction_in_range, default_value_t = 0.5)]
+    pub iupac_single: f64,
+
+    /// minimum combined frequency of the two most common nucleotides written as a two nucleotide IUPAC code
+    #[arg(long = "iupac-pair", value_parser = fraction_in_range, default_value_t = 0.75)]
+    pub iupac_pair: f64,
+
+    /// order of the Markov background model kmers are ranked against
+    #[arg(long = "background-order", value_parser = background_order_in_range, default_value_t = 0)]
+    background_order: usize,
+
+    /// estimate the background model from this FASTA file instead of the input
+    #[arg(long = "background-file")]
+    background_file: Option<String>,
+
+    /// seed of the random number generator, so that runs can be repeated with the same results
+    #[arg(long = "seed")]
+    pub seed: Option<u64>,
+
     /// save motifs to file
     #[arg(short = 'o', long = "output")]
     output_file: Option<Option<String>>,
+
+    /// draw a sequence logo of every motif in SVG, saved as PREFIX-1.svg, PREFIX-2.svg, ...
+    #[arg(long = "logo", value_name = "PREFIX")]
+    logo: Option<Option<String>>,
+
+    /// format of the output file: the text report, or the motif sites as BED or GFF3
+    #[arg(long = "output-format", value_enum, default_value_t = OutputFormat::Text)]
+    output_format: OutputFormat,
 }
 const K_RANGE: RangeInclusive<usize> = 1..=64;
 
@@ -156,9 +716,38 @@ fn k_in_range(s: &str) -> Result<usize, String> {
         ))
     }
 }
-#[derive(Subcommand, Debug, Clone)]
+const BACKGROUND_ORDER_RANGE: RangeInclusive<usize> = 0..=5;
+
+fn background_order_in_range(s: &str) -> Result<usize, String> {
+    let order: usize = s
+        .parse()
+        .map_err(|_| format!("`{s}` isn't a valid background order"))?;
+    if BACKGROUND_ORDER_RANGE.contains(&order) {
+        Ok(order)
+    } else {
+        Err(format!(
+            "background order not in range {}-{}",
+            BACKGROUND_ORDER_RANGE.start(),
+            BACKGROUND_ORDER_RANGE.end()
+        ))
+    }
+}
+
+fn fraction_in_range(s: &str) -> Result<f64, String> {
+    let fraction: f64 = s
+        .parse()
+        .map_err(|_| format!("`{s}` isn't a valid frequency"))?;
+    if (0.0..=1.0).contains(&fraction) {
+        Ok(fraction)
+    } else {
+        Err("frequency not in range 0-1".to_string())
+    }
+}
+#[derive(Subcommand, Debug, Clone, Serialize)]
+#[serde(tag = "name")]
 pub enum Commands {
     #[clap(name = "gibbs", about = "Run the Gibbs Sampler algorithm")]
+    #[serde(rename = "gibbs")]
     GibbsSampler {
         /// number of runs
         #[arg(short = 'r', long = "runs")]
@@ -169,22 +758,120 @@ pub enum Commands {
         num_iterations: usize,
     },
 
+    #[clap(
+        name = "em",
+        about = "Run the Expectation Maximization algorithm (MEME-style, one site per sequence)"
+    )]
+    #[serde(rename = "em")]
+    ExpectationMaximization {
+        /// number of runs, each seeded from a random kmer
+        #[arg(short = 'r', long = "runs")]
+        num_runs: usize,
+
+        /// maximum number of iterations per run
+        #[arg(short = 't', long = "iters")]
+        num_iterations: usize,
+    },
+
     #[clap(
         name = "median",
         about = "Run the Median String algorithm (Warning: this can take a long time to run for large values of k)"
     )]
+    #[serde(rename = "median")]
     MedianString,
 
     #[clap(
         name = "randomized",
         about = "Run the Randomized Motif Search algorithm"
     )]
+    #[serde(rename = "randomized")]
     Randomized {
         /// number of runs
         #[arg(short = 'r', long = "runs")]
         num_runs: usize,
     },
+    #[clap(
+        name = "discriminative",
+        about = "Find the kmer most enriched in the input compared to a control set of sequences"
+    )]
+    #[serde(rename = "discriminative")]
+    Discriminative {
+        /// FASTA file of control sequences, e.g. random genomic regions or non-regulated promoters
+        #[arg(short = 'c', long = "control")]
+        control: String,
+    },
+    #[clap(
+        name = "ensemble",
+        about = "Run several algorithms on the same sequences and report the motifs they agree on"
+    )]
+    #[serde(rename = "ensemble")]
+    Ensemble {
+        /// algorithms to run, separated by commas
+        #[arg(
+            short = 'm',
+            long = "algorithms",
+            value_enum,
+            value_delimiter = ',',
+            default_value = "gibbs,randomized,median"
+        )]
+        algorithms: Vec<EnsembleAlgorithm>,
+
+        /// number of runs of the gibbs, em and randomized algorithms
+        #[arg(short = 'r', long = "runs", default_value_t = 20)]
+        num_runs: usize,
+
+        /// number of iterations per run of the gibbs and em algorithms
+        #[arg(short = 't', long = "iters", default_value_t = 100)]
+        num_iterations: usize,
+
+        /// most mismatches between the consensus strings of motifs counted as the same motif
+        #[arg(short = 'd', long = "max-distance", default_value_t = 1)]
+        max_distance: usize,
+
+        /// report motifs found by at least this many algorithms
+        #[arg(short = 's', long = "min-support", default_value_t = 2)]
+        min_support: usize,
+    },
+    #[clap(
+        name = "scan",
+        about = "Scan the input for sites of a position weight matrix, scored against the zero order composition"
+    )]
+    #[serde(rename = "scan")]
+    Scan {
+        /// matrix file in JASPAR, TRANSFAC or HOMER format, or the results file of a previous run
+        matrix: String,
+
+        /// format of the matrix file, guessed from its contents by default
+        #[arg(long = "matrix-format", value_enum)]
+        matrix_format: Option<MotifFormat>,
+
+        /// report sites with a log-odds score of at least this many bits
+        #[arg(long = "min-score", conflicts_with = "max_p_value")]
+        min_score: Option<f64>,
+
+        /// report sites with a p-value of at most this (the default, at 1e-4)
+        #[arg(long = "max-p-value")]
+        max_p_value: Option<f64>,
+    },
+    #[clap(
+        name = "logo",
+        about = "Draw sequence logos in SVG of saved motifs (takes no input file)"
+    )]
+    #[serde(rename = "logo")]
+    Logo {
+        /// matrix file in JASPAR, TRANSFAC or HOMER format, or the results file of a previous run
+        matrix: String,
+
+        /// format of the matrix file, guessed from its contents by default
+        #[arg(long = "matrix-format", value_enum)]
+        matrix_format: Option<MotifFormat>,
+
+        /// logos are saved as PREFIX-1.svg, PREFIX-2.svg, ... in the order of the motifs in the file
+        #[arg(short = 'p', long = "prefix", default_value = "logo")]
+        prefix: String,
+    },
     #[clap(name = "find_motif", about = "Find a motif in a genome")]
+    #[serde(rename = "find_motif")]
     FindMotif {
         /// motif to find
         motif: String,
@@ -197,7 +884,125 @@ pub enum Commands {
 
 pub struct Summary {
     pub consensus_string: String,
+    pub degenerate_consensus: String,
     pub unique_motifs: String,
     pub best_motif: Option<String>,
     pub best_motif_score: Option<isize>,
+    /// the best local alignment score and motif of up to five unique motifs, with `-a`
+    pub top_motifs: Option<Vec<(isize, String)>>,
+    pub site_posteriors: Option<Vec<f64>>,
+    pub motif_score: Option<(ScoringMethod, f64)>,
+    pub enrichment: Option<EnrichedKmer>,
+    pub significance: Option<Significance>,
+    pub hits: Option<Vec<Hit>>,
+    /// score of the best run after every iteration, for the algorithms that iterate
+    pub score_trace: Option<Vec<f64>>,
+    /// the motif of every algorithm that found this one, for the ensemble command
+    pub ensemble: Option<Vec<EnsembleMember>>,
+}
+
+/// An algorithm the ensemble command can run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EnsembleAlgorithm {
+    #[value(name = "gibbs")]
+    Gibbs,
+    #[value(name = "em")]
+    Em,
+    #[value(name = "median")]
+    Median,
+    #[value(name = "randomized")]
+    Randomized,
+}
+
+impl Display for EnsembleAlgorithm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EnsembleAlgorithm::Gibbs => write!(f, "gibbs"),
+            EnsembleAlgorithm::Em => write!(f, "em"),
+            EnsembleAlgorithm::Median => write!(f, "median"),
+            EnsembleAlgorithm::Randomized => write!(f, "randomized"),
+        }
+    }
+}
+
+impl EnsembleAlgorithm {
+    fn algorithm(&self, runs: usize, iterations: usize) -> Box<dyn MotifAlgorithm> {
+        match self {
+            EnsembleAlgorithm::Gibbs => Box::new(GibbsSampler { runs, iterations }),
+            EnsembleAlgorithm::Em => Box::new(ExpectationMaximization { runs, iterations }),
+            EnsembleAlgorithm::Median => Box::new(MedianString),
+            EnsembleAlgorithm::Randomized => Box::new(RandomizedMotifSearch { runs }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{env, fs};
+
+    use clap::Parser;
+
+    use super::MotifFinder;
+    use crate::Error;
+
+    fn run(args: &[&str]) -> Result<(), Error> {
+        let args = ["motif_finder"].iter().chain(args);
+        MotifFinder::try_parse_from(args).unwrap().exec()
+    }
+
+    fn matrix_file(name: &str) -> String {
+        let path =
+            env::temp_dir().join(format!("motif_finder-{}-{name}.jaspar", std::process::id()));
+        fs::write(
+            &path,
+            ">TATA\nA [ 0 10 0 10 ]\nC [ 0 0 0 0 ]\nG [ 0 0 0 0 ]\nT [ 10 0 10 0 ]\n",
+        )
+        .unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    #[test]
+    pub fn test_scan_options() {
+        let matrix = matrix_file("scan");
+        assert!(run(&["promoters.fasta", "-e", "2", "scan", &matrix]).is_ok());
+        for options in [
+            &["--output-format", "meme"][..],
+            &["--output-format", "html"],
+            &["-n", "2"],
+            &["--shuffles", "2"],
+            &["--background-order", "1"],
+        ] {
+            let mut args = vec!["promoters.fasta", "-e", "2"];
+            args.extend(options);
+            args.extend(["scan", &matrix]);
+            assert!(run(&args).is_err());
+        }
+    }
+
+    #[test]
+    pub fn test_logo_without_input() {
+        let matrix = matrix_file("logo");
+        let prefix = env::temp_dir().join(format!("motif_finder-{}-logo", std::process::id()));
+        let prefix = prefix.to_string_lossy();
+        assert!(run(&["logo", &matrix, "--prefix", &prefix]).is_ok());
+        assert!(fs::metadata(format!("{prefix}-1.svg")).is_ok());
+        // every other command still needs the sequences and how many to read
+        assert!(run(&["randomized", "-r", "2"]).is_err());
+        assert!(run(&["promoters.fasta", "randomized", "-r", "2"]).is_err());
+    }
+
+    #[test]
+    pub fn test_single_motif_commands() {
+        assert!(run(&["promoters.fasta", "-e", "2", "find_motif", "TATAAA", "0"]).is_ok());
+        for options in [&["-n", "2"][..], &["--shuffles", "2"]] {
+            let mut args = vec!["promoters.fasta", "-e", "2", "-k", "6"];
+            args.extend(options);
+            let mut ensemble = args.clone();
+            ensemble.extend(["ensemble", "-r", "2"]);
+            assert!(run(&ensemble).is_err());
+            args.extend(["find_motif", "TATAAA", "0"]);
+            assert!(run(&args).is_err());
+        }
+    }
 }
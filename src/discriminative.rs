@@ -0,0 +1,176 @@
+use std::collections::{HashMap, HashSet};
+
+use rayon::prelude::*;
+use tracing::info;
+
+use crate::sequence::{candidate_kmers, MotifInstance};
+use crate::{Error, SearchOptions};
+use serde::Serialize;
+
+/// A kmer with the number of input and control sequences it occurs in
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EnrichedKmer {
+    pub kmer: String,
+    /// number of input sequences containing the kmer
+    pub input_count: usize,
+    pub input_total: usize,
+    /// number of control sequences containing the kmer
+    pub control_count: usize,
+    pub control_total: usize,
+    /// probability of the kmer occurring in at least `input_count` input sequences if it was
+    /// spread over the input and control sequences at random (one-sided Fisher exact test)
+    pub p_value: f64,
+}
+
+#[tracing::instrument(skip_all)]
+fn sequences_containing(
+    sequences: &[String],
+    k: usize,
+    both_strands: bool,
+) -> HashMap<String, usize> {
+    // count every kmer once per sequence it is found in
+    let mut counts = HashMap::new();
+    for (index, seq) in sequences.iter().enumerate() {
+        let kmers: HashSet<String> = candidate_kmers(index, seq, k, both_strands)
+            .into_iter()
+            .map(|kmer| kmer.instance.kmer)
+            .collect();
+        for kmer in kmers {
+            *counts.entry(kmer).or_insert(0) += 1;
+        }
+    }
+    counts
+}
+
+/// P(X >= observed) for X hypergeometric, drawing `draws` items from `population` items of which `successes` are successes
+fn hypergeometric_upper_tail(
+    ln_factorials: &[f64],
+    population: usize,
+    successes: usize,
+    draws: usize,
+    observed: usize,
+) -> f64 {
+    let ln_choose = |n: usize, r: usize| ln_factorials[n] - ln_factorials[r] - ln_factorials[n - r];
+    let ln_total = ln_choose(population, draws);
+    let lowest = observed.max((draws + successes).saturating_sub(population));
+    (lowest..=successes.min(draws))
+        .map(|x| {
+            (ln_choose(successes, x) + ln_choose(population - successes, draws - x) - ln_total)
+                .exp()
+        })
+        .sum::<f64>()
+        .min(1.0)
+}
+
+/// Rank every kmer of the input by how enriched it is in the input sequences compared to the control sequences
+///
+/// The most significant kmers come first.
+#[tracing::instrument(skip(dna, control))]
+pub fn enriched_kmers(
+    dna: &[String],
+    control: &[String],
+    k: usize,
+    both_strands: bool,
+) -> Vec<EnrichedKmer> {
+    let input_counts = sequences_containing(dna, k, both_strands);
+    let control_counts = sequences_containing(control, k, both_strands);
+    let population = dna.len() + control.len();
+    let mut ln_factorials = vec![0.0; population + 1];
+    for i in 1..=population {
+        ln_factorials[i] = ln_factorials[i - 1] + (i as f64).ln();
+    }
+    let mut enriched: Vec<EnrichedKmer> = input_counts
+        .into_par_iter()
+        .map(|(kmer, input_count)| {
+            let control_count = control_counts.get(&kmer).copied().unwrap_or(0);
+            let p_value = hypergeometric_upper_tail(
+                &ln_factorials,
+                population,
+                input_count + control_count,
+                dna.len(),
+                input_count,
+            );
+            EnrichedKmer {
+                kmer,
+                input_count,
+                input_total: dna.len(),
+                control_count,
+                control_total: control.len(),
+                p_value,
+            }
+        })
+        .collect();
+    enriched.par_sort_by(|a, b| {
+        a.p_value
+            .total_cmp(&b.p_value)
+            .then(b.input_count.cmp(&a.input_count))
+            .then(a.kmer.cmp(&b.kmer))
+    });
+    enriched
+}
+
+#[tracing::instrument(skip(dna, control, options))]
+pub fn discriminative_motif_search(
+    dna: &[String],
+    control: &[String],
+    k: usize,
+    options: &SearchOptions,
+) -> Result<(Vec<MotifInstance>, EnrichedKmer), Error> {
+    // the motif is the most enriched kmer and its sites are all of its occurrences in the input
+    info!(
+        "Comparing {} input sequences against {} control sequences",
+        dna.len(),
+        control.len()
+    );
+    let enriched = enriched_kmers(dna, control, k, options.both_strands);
+    let progress = options.progress();
+    progress.message("Top 5 enriched kmers:");
+    for kmer in enriched.iter().take(5) {
+        progress.message(&format!(
+            "{}: {}/{} input, {}/{} control, p-value {:.3e}",
+            kmer.kmer,
+            kmer.input_count,
+            kmer.input_total,
+            kmer.control_count,
+            kmer.control_total,
+            kmer.p_value
+        ));
+    }
+    let best = enriched.into_iter().next().ok_or(Error::NoMotifsFound)?;
+    let motifs = dna
+        .iter()
+        .enumerate()
+        .flat_map(|(index, seq)| candidate_kmers(index, seq, k, options.both_strands))
+        .filter(|kmer| kmer.instance.kmer == best.kmer)
+        .map(|kmer| kmer.instance)
+        .collect();
+    Ok((motifs, best))
+}
+
+#[cfg(test)]
+mod test {
+    use super::enriched_kmers;
+
+    #[test]
+    pub fn test_enriched_kmers() {
+        let dna = vec![
+            "CCTTGACAGG".to_string(),
+            "TTGACACCCCAA".to_string(),
+            "GGGTTGACAC".to_string(),
+        ];
+        let control = vec![
+            "CCCCAAAAGG".to_string(),
+            "AAAAACCCCT".to_string(),
+            "GGGGTTTTCA".to_string(),
+        ];
+        let enriched = enriched_kmers(&dna, &control, 6, false);
+        assert_eq!(enriched[0].kmer, "TTGACA");
+        assert_eq!(enriched[0].input_count, 3);
+        assert_eq!(enriched[0].control_count, 0);
+        // 1 in C(6, 3) ways of spreading three sequences over six
+        assert!((enriched[0].p_value - 0.05).abs() < 1e-9);
+        // CCCCAA is in one sequence of each set so it isn't enriched
+        let shared = enriched.iter().find(|kmer| kmer.kmer == "CCCCAA").unwrap();
+        assert!((shared.p_value - 0.8).abs() < 1e-9);
+    }
+}
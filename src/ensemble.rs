@@ -0,0 +1,210 @@
+use std::collections::HashSet;
+
+use serde::Serialize;
+
+use crate::algorithm::{MotifAlgorithm, MotifResult, SearchConfig};
+use crate::motif::{Alphabet, Motif, MotifSet};
+use crate::sequence::{reverse_complement, MotifInstance, Strand};
+use crate::Error;
+
+/// The motif one algorithm found, as a member of an ensemble motif
+#[derive(Debug, Clone, Serialize)]
+pub struct EnsembleMember {
+    pub algorithm: String,
+    /// consensus of the sites the algorithm found
+    pub consensus: String,
+    /// whether the sites are turned to the other strand to line up with the first member
+    pub reverse_complement: bool,
+    /// score of the sites the algorithm found, with the scoring method of the search
+    pub score: f64,
+    #[serde(skip)]
+    pub motifs: Vec<MotifInstance>,
+}
+
+impl EnsembleMember {
+    /// Consensus on the strand of the first member
+    fn aligned_consensus(&self) -> Result<Motif, Error> {
+        let consensus = Motif::new(&self.consensus, Alphabet::Dna)?;
+        Ok(if self.reverse_complement {
+            consensus.reverse_complement()
+        } else {
+            consensus
+        })
+    }
+}
+
+/// Motifs of different algorithms whose consensus strings are close enough to be the same motif
+#[derive(Debug, Clone, Default)]
+pub struct EnsembleMotif {
+    pub members: Vec<EnsembleMember>,
+}
+
+impl EnsembleMotif {
+    /// Number of algorithms that found the motif
+    pub fn support(&self) -> usize {
+        self.members
+            .iter()
+            .map(|member| &member.algorithm)
+            .collect::<HashSet<_>>()
+            .len()
+    }
+
+    /// Sites of all the members on the strand of the first, with the sites found by several algorithms once
+    pub fn sites(&self) -> Vec<MotifInstance> {
+        let mut seen = HashSet::new();
+        self.members
+            .iter()
+            .flat_map(|member| {
+                member
+                    .motifs
+                    .iter()
+                    .map(|motif| match member.reverse_complement {
+                        true => other_strand(motif),
+                        false => motif.clone(),
+                    })
+            })
+            .filter(|site| seen.insert(site.clone()))
+            .collect()
+    }
+}
+
+/// The same site read from the other strand
+fn other_strand(motif: &MotifInstance) -> MotifInstance {
+    MotifInstance {
+        kmer: reverse_complement(&motif.kmer),
+        strand: match motif.strand {
+            Strand::Forward => Strand::Reverse,
+            Strand::Reverse => Strand::Forward,
+        },
+        position: motif.position,
+    }
+}
+
+/// Group the motifs found by the algorithms, in the order of `run_algorithms`, into ensemble motifs
+///
+/// A motif joins the first ensemble motif with a member at most `max_distance` mismatches away from its consensus,
+/// or from the reverse complement of its consensus when both strands are searched, and starts a new one otherwise.
+/// The ensemble motifs found by the most algorithms come first.
+pub fn ensemble_motifs(
+    algorithms: &[Box<dyn MotifAlgorithm>],
+    results: &[MotifResult],
+    config: &SearchConfig,
+    max_distance: usize,
+) -> Result<Vec<EnsembleMotif>, Error> {
+    let options = &config.options;
+    let orientations: &[bool] = if options.both_strands {
+        &[false, true]
+    } else {
+        &[false]
+    };
+    let mut ensemble: Vec<EnsembleMotif> = vec![];
+    for (algorithm, result) in algorithms.iter().zip(results) {
+        if result.motifs.is_empty() {
+            continue;
+        }
+        let sites = MotifSet::new(result.motifs.clone())?;
+        let consensus = sites.consensus()?;
+        let mut member = EnsembleMember {
+            algorithm: algorithm.name().to_string(),
+            consensus: consensus.to_string(),
+            reverse_complement: false,
+            score: sites.score(options.scoring, &options.background),
+            motifs: result.motifs.clone(),
+        };
+        let mut joined = None;
+        'search: for (i, motif) in ensemble.iter().enumerate() {
+            for &reverse in orientations {
+                let aligned = match reverse {
+                    true => consensus.reverse_complement(),
+                    false => consensus.clone(),
+                };
+                for other in &motif.members {
+                    let distance = aligned.hamming_distance(&other.aligned_consensus()?);
+                    if distance.is_some_and(|distance| distance <= max_distance) {
+                        joined = Some((i, reverse));
+                        break 'search;
+                    }
+                }
+            }
+        }
+        match joined {
+            Some((i, reverse)) => {
+                member.reverse_complement = reverse;
+                ensemble[i].members.push(member);
+            }
+            None => ensemble.push(EnsembleMotif {
+                members: vec![member],
+            }),
+        }
+    }
+    // stable, so motifs with the same support stay in the order of the algorithms
+    ensemble.sort_by_key(|motif| std::cmp::Reverse(motif.support()));
+    Ok(ensemble)
+}
+
+#[cfg(test)]
+mod test {
+    use super::ensemble_motifs;
+    use crate::algorithm::{
+        GibbsSampler, MedianString, MotifAlgorithm, MotifResult, RandomizedMotifSearch,
+        SearchConfig,
+    };
+    use crate::sequence::{MotifInstance, Strand};
+    use crate::SearchOptions;
+
+    fn result(kmers: &[&str]) -> MotifResult {
+        MotifResult::new(
+            kmers
+                .iter()
+                .map(|kmer| MotifInstance::new(kmer.to_string(), Strand::Forward))
+                .collect(),
+        )
+    }
+
+    #[test]
+    pub fn test_ensemble_motifs() {
+        let algorithms: Vec<Box<dyn MotifAlgorithm>> = vec![
+            Box::new(RandomizedMotifSearch { runs: 1 }),
+            Box::new(GibbsSampler {
+                runs: 1,
+                iterations: 1,
+            }),
+            Box::new(MedianString),
+        ];
+        let results = vec![
+            result(&["TTTTTT", "TTTTTT", "TTTTTA"]),
+            result(&["ACGTAC", "ACGTAC", "ACGTTC"]),
+            // the reverse complement of the motif of the Gibbs sampler, one mismatch away
+            result(&["GTACGA"]),
+        ];
+        let mut config = SearchConfig {
+            k: 6,
+            ..Default::default()
+        };
+        let ensemble = ensemble_motifs(&algorithms, &results, &config, 1).unwrap();
+        assert_eq!(ensemble.len(), 3);
+        assert!(ensemble.iter().all(|motif| motif.support() == 1));
+
+        config.options = SearchOptions {
+            both_strands: true,
+            ..Default::default()
+        };
+        let ensemble = ensemble_motifs(&algorithms, &results, &config, 1).unwrap();
+        assert_eq!(ensemble.len(), 2);
+        assert_eq!(ensemble[0].support(), 2);
+        assert_eq!(ensemble[0].members[0].algorithm, "Gibbs Sampler");
+        assert!(ensemble[0].members[1].reverse_complement);
+        // the repeated site is kept once and the site of the median string is turned around
+        let sites: Vec<String> = ensemble[0]
+            .sites()
+            .into_iter()
+            .map(|site| site.kmer)
+            .collect();
+        assert_eq!(sites, vec!["ACGTAC", "ACGTTC", "TCGTAC"]);
+        assert_eq!(ensemble[1].members[0].consensus, "TTTTTT");
+        assert!(ensemble_motifs(&algorithms, &results, &config, 0)
+            .unwrap()
+            .iter()
+            .all(|motif| motif.support() == 1));
+    }
+}
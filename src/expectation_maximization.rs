@@ -0,0 +1,225 @@
+use crate::sequence::{Candidate, MotifInstance};
+use crate::site_model::sequence_candidates;
+use crate::Error;
+use crate::{generate_probability, generate_profile_given_motif_matrix, run_rng, SearchOptions};
+use rand::Rng;
+use rayon::prelude::*;
+use tracing::{info, trace};
+
+// stop iterating once no profile entry moves by more than this between iterations
+const CONVERGENCE_THRESHOLD: f64 = 1e-6;
+
+/// Result of fitting a motif with expectation maximization
+#[derive(Debug, Clone)]
+pub struct EmResult {
+    /// the most probable site of each sequence under the final profile
+    pub motifs: Vec<MotifInstance>,
+    /// the posterior probability of each candidate site, per sequence, in the order
+    /// the sites appear in the sequence (forward then reverse complement at each position
+    /// when both strands are searched), skipping kmers that contain masked nucleotides
+    pub posteriors: Vec<Vec<f64>>,
+    /// the fitted position weight matrix, one row per nucleotide (ACGT)
+    pub profile: Vec<Vec<f64>>,
+    /// log likelihood of the sequences under the fitted motif relative to the background model
+    pub log_likelihood: f64,
+    /// log likelihood at the seed and after every iteration, higher is better
+    pub score_trace: Vec<f64>,
+}
+
+#[tracing::instrument(skip_all)]
+fn expectation_step(
+    candidates: &[Vec<Candidate>],
+    background_probabilities: &[Vec<f64>],
+    profile: &[Vec<f64>],
+) -> (Vec<Vec<f64>>, f64) {
+    // the posterior of every site is its likelihood ratio against the background normalized over the sequence
+    let mut posteriors = vec![];
+    let mut log_likelihood = 0.0;
+    for (kmers, background) in candidates.iter().zip(background_probabilities) {
+        if kmers.is_empty() {
+            posteriors.push(vec![]);
+            continue;
+        }
+        let ratios: Vec<f64> = kmers
+            .iter()
+            .zip(background)
+            .map(|(kmer, p)| generate_probability(&kmer.instance.kmer, profile) / p)
+            .collect();
+        let sum: f64 = ratios.iter().sum();
+        log_likelihood += (sum / kmers.len() as f64).ln();
+        posteriors.push(ratios.iter().map(|p| p / sum).collect());
+    }
+    (posteriors, log_likelihood)
+}
+
+#[tracing::instrument(skip_all)]
+fn maximization_step(
+    candidates: &[Vec<Candidate>],
+    posteriors: &[Vec<f64>],
+    background: &[f64; 4],
+    k: usize,
+) -> Vec<Vec<f64>> {
+    // expected counts of every nucleotide per position, weighted by the site posteriors
+    // a single pseudocount is spread according to the background so sparse columns stay close to it
+    let mut counts: Vec<Vec<f64>> = background.iter().map(|&p| vec![p; k]).collect();
+    for (kmers, weights) in candidates.iter().zip(posteriors) {
+        for (kmer, weight) in kmers.iter().zip(weights) {
+            for (i, nuc) in kmer.instance.kmer.chars().enumerate() {
+                let nuc_index = match nuc {
+                    'A' => 0,
+                    'C' => 1,
+                    'G' => 2,
+                    'T' => 3,
+                    _ => continue,
+                };
+                counts[nuc_index][i] += weight;
+            }
+        }
+    }
+    for i in 0..k {
+        let sum: f64 = counts.iter().map(|row| row[i]).sum();
+        for row in counts.iter_mut() {
+            row[i] /= sum;
+        }
+    }
+    counts
+}
+
+#[tracing::instrument(skip_all)]
+fn expectation_maximization(
+    candidates: &[Vec<Candidate>],
+    background_probabilities: &[Vec<f64>],
+    seed: &MotifInstance,
+    background: &[f64; 4],
+    k: usize,
+    iterations: usize,
+) -> Result<EmResult, Error> {
+    // start from the profile of a single kmer and alternate between estimating where the sites are
+    // and re-estimating the profile from those expected sites
+    let mut profile = generate_profile_given_motif_matrix(&[seed], true)?;
+    let (mut posteriors, mut log_likelihood) =
+        expectation_step(candidates, background_probabilities, &profile);
+    let mut score_trace = vec![log_likelihood];
+    for _j in 0..iterations {
+        trace!("Expectation maximization iteration: {}", _j);
+        let new_profile = maximization_step(candidates, &posteriors, background, k);
+        let change = new_profile
+            .iter()
+            .flatten()
+            .zip(profile.iter().flatten())
+            .map(|(a, b)| (a - b).abs())
+            .fold(0.0, f64::max);
+        profile = new_profile;
+        (posteriors, log_likelihood) =
+            expectation_step(candidates, background_probabilities, &profile);
+        score_trace.push(log_likelihood);
+        if change < CONVERGENCE_THRESHOLD {
+            break;
+        }
+    }
+    let motifs = candidates
+        .iter()
+        .zip(&posteriors)
+        .filter(|(kmers, _)| !kmers.is_empty())
+        .map(|(kmers, weights)| {
+            let (best, _) = weights
+                .iter()
+                .enumerate()
+                .fold((0, f64::MIN), |best, (i, &weight)| {
+                    if weight > best.1 {
+                        (i, weight)
+                    } else {
+                        best
+                    }
+                });
+            kmers[best].instance.clone()
+        })
+        .collect();
+    Ok(EmResult {
+        motifs,
+        posteriors,
+        profile,
+        log_likelihood,
+        score_trace,
+    })
+}
+
+#[tracing::instrument(skip(dna, options))]
+pub fn iterate_expectation_maximization(
+    dna: &[String],
+    k: usize,
+    iterations: usize,
+    runs: usize,
+    options: &SearchOptions,
+) -> Result<EmResult, Error> {
+    // every run is seeded from a randomly chosen kmer in the input and the most likely fit is kept
+    info!("Initializing Expectation Maximization");
+    let candidates = sequence_candidates(dna, k, options.both_strands)?;
+    let seeds: Vec<&MotifInstance> = candidates
+        .iter()
+        .flatten()
+        .map(|kmer| &kmer.instance)
+        .collect();
+    let background = options.background.frequencies();
+    let background_probabilities: Vec<Vec<f64>> = candidates
+        .iter()
+        .map(|kmers| {
+            kmers
+                .iter()
+                .map(|kmer| options.background.probability(&kmer.instance.kmer))
+                .collect()
+        })
+        .collect();
+    let progress = options.progress();
+    progress.start(
+        &format!("Starting Expectation Maximization with {runs} runs and {iterations} iterations"),
+        runs,
+    );
+
+    let mut result: Vec<EmResult> = (1..=runs)
+        .into_par_iter()
+        .map(|i| {
+            let seed = seeds[run_rng(options.seed, i).gen_range(0..seeds.len())];
+            let run = expectation_maximization(
+                &candidates,
+                &background_probabilities,
+                seed,
+                &background,
+                k,
+                iterations,
+            );
+            progress.advance(1);
+            run
+        })
+        .collect::<Result<Vec<EmResult>, Error>>()?;
+    result.par_sort_by(|a, b| b.log_likelihood.total_cmp(&a.log_likelihood));
+    let best = result.swap_remove(0);
+    progress.finish(&format!(
+        "Done! Best log likelihood: {:.3}",
+        best.log_likelihood
+    ));
+    Ok(best)
+}
+
+#[cfg(test)]
+mod test {
+    use super::iterate_expectation_maximization;
+
+    #[test]
+    pub fn test_posteriors_sum_to_one() {
+        let dna = vec![
+            "TTTTACGTACGTTTTT".to_string(),
+            "GGACGTACGTGGGGGG".to_string(),
+            "CCCCCCCACGTACGTC".to_string(),
+        ];
+        // shifted copies of the motif are local optima, so it takes several runs to find the best fit
+        let result =
+            iterate_expectation_maximization(&dna, 8, 50, 50, &Default::default()).unwrap();
+        assert_eq!(result.motifs.len(), 3);
+        for posteriors in &result.posteriors {
+            let sum: f64 = posteriors.iter().sum();
+            assert!((sum - 1.0).abs() < 1e-9);
+        }
+        assert!(result.motifs.iter().all(|motif| motif.kmer == "ACGTACGT"));
+    }
+}
@@ -0,0 +1,470 @@
+use std::fmt::Write;
+
+use crate::command::{Commands, Summary};
+use crate::logo::{escape_xml, render_logo};
+use crate::sequence::{MotifInstance, SequenceRecord, Strand};
+use crate::utils::results_meme_motifs;
+use crate::Error;
+
+// the report is read offline, so styles live in the page and figures are inline SVG
+const STYLE: &str = "body { font-family: Arial, Helvetica, sans-serif; margin: 2em; color: #222; }
+table { border-collapse: collapse; margin-bottom: 1em; }
+th, td { border: 1px solid #ccc; padding: 0.2em 0.6em; text-align: left; vertical-align: top; }
+th { background: #f0f0f0; }
+td.sequence, pre { font-family: Menlo, Consolas, monospace; }
+pre { margin: 0; }
+section { border-top: 2px solid #255C99; margin-top: 2em; }
+figure { margin: 0 0 1em 0; overflow-x: auto; }";
+
+const FONT: &str = "Arial, Helvetica, sans-serif";
+const FORWARD_COLOR: &str = "#255C99";
+const REVERSE_COLOR: &str = "#D62839";
+
+const MAP_LABEL_WIDTH: f64 = 160.0;
+const MAP_WIDTH: f64 = 800.0;
+const MAP_ROW_HEIGHT: f64 = 24.0;
+const MAP_SITE_HEIGHT: f64 = 8.0;
+
+const PLOT_LEFT: f64 = 80.0;
+const PLOT_WIDTH: f64 = 600.0;
+const PLOT_HEIGHT: f64 = 200.0;
+const PLOT_MARGIN: f64 = 20.0;
+
+/// A site or pattern match drawn on the site map
+struct MapSite<'a> {
+    sequence: usize,
+    start: usize,
+    end: usize,
+    strand: Strand,
+    site: &'a str,
+}
+
+/// Render the run as a single HTML page
+///
+/// `header` holds the parameters of the run in the `key: value` lines of the text report header.
+pub(crate) fn render_report(
+    header: &str,
+    results: &[(Vec<MotifInstance>, Summary)],
+    records: &[SequenceRecord],
+    command: &Commands,
+) -> Result<String, Error> {
+    let logos = results_meme_motifs(results, command)?;
+    // writing to a String can't fail
+    let mut html = String::new();
+    writeln!(html, "<!DOCTYPE html>").unwrap();
+    writeln!(html, r#"<html lang="en">"#).unwrap();
+    writeln!(html, "<head>").unwrap();
+    writeln!(html, r#"<meta charset="utf-8">"#).unwrap();
+    writeln!(html, "<title>MotifFinder report</title>").unwrap();
+    writeln!(html, "<style>\n{STYLE}\n</style>").unwrap();
+    writeln!(html, "</head>").unwrap();
+    writeln!(html, "<body>").unwrap();
+    writeln!(html, "<h1>MotifFinder report</h1>").unwrap();
+    writeln!(html, "<h2>Parameters</h2>").unwrap();
+    writeln!(html, "<table>").unwrap();
+    for line in header.lines() {
+        match line.split_once(": ") {
+            Some((key, value)) => writeln!(
+                html,
+                "<tr><th>{}</th><td>{}</td></tr>",
+                escape_xml(key),
+                escape_xml(value)
+            )
+            .unwrap(),
+            None => writeln!(
+                html,
+                r#"<tr><th colspan="2">{}</th></tr>"#,
+                escape_xml(line)
+            )
+            .unwrap(),
+        }
+    }
+    writeln!(html, "</table>").unwrap();
+    for (i, ((motifs, summary), logo)) in results.iter().zip(&logos).enumerate() {
+        writeln!(html, "<section>").unwrap();
+        writeln!(
+            html,
+            "<h2>Motif {} of {}: {}</h2>",
+            i + 1,
+            results.len(),
+            escape_xml(&summary.degenerate_consensus)
+        )
+        .unwrap();
+        write_summary(&mut html, motifs, summary);
+        writeln!(html, "<h3>Logo</h3>").unwrap();
+        writeln!(
+            html,
+            "<figure>\n{}</figure>",
+            render_logo(&logo.name, &logo.profile)
+        )
+        .unwrap();
+        writeln!(html, "<h3>Sites</h3>").unwrap();
+        let sites: Vec<MapSite> = match (command, &summary.hits) {
+            (Commands::FindMotif { .. }, Some(hits)) => hits
+                .iter()
+                .map(|hit| MapSite {
+                    sequence: hit.sequence,
+                    start: hit.start,
+                    end: hit.end,
+                    strand: Strand::Forward,
+                    site: &hit.site,
+                })
+                .collect(),
+            _ => motifs
+                .iter()
+                .filter_map(|motif| {
+                    let position = motif.position?;
+                    Some(MapSite {
+                        sequence: position.sequence,
+                        start: position.offset,
+                        end: position.offset + motif.kmer.len(),
+                        strand: motif.strand,
+                        site: &motif.kmer,
+                    })
+                })
+                .collect(),
+        };
+        write_site_map(&mut html, &sites, records);
+        if let Some(score_trace) = &summary.score_trace {
+            writeln!(html, "<h3>Score convergence</h3>").unwrap();
+            let label = match command {
+                Commands::ExpectationMaximization { .. } => {
+                    "log likelihood of the best run, higher is better"
+                }
+                _ => "score of the best run, lower is better",
+            };
+            write_convergence_plot(&mut html, score_trace, label);
+        }
+        write_alignments(&mut html, summary);
+        writeln!(html, "</section>").unwrap();
+    }
+    writeln!(html, "</body>").unwrap();
+    writeln!(html, "</html>").unwrap();
+    Ok(html)
+}
+
+fn write_summary(html: &mut String, motifs: &[MotifInstance], summary: &Summary) {
+    let mut rows = vec![
+        ("Consensus string", summary.consensus_string.clone()),
+        (
+            "Degenerate consensus (IUPAC)",
+            summary.degenerate_consensus.clone(),
+        ),
+        ("Unique motifs", summary.unique_motifs.clone()),
+        ("Sites", motifs.len().to_string()),
+    ];
+    if let Some((scoring, score)) = summary.motif_score {
+        rows.push(("Score", format!("{score:.3} ({scoring})")));
+    }
+    if let Some(enrichment) = &summary.enrichment {
+        rows.push((
+            "Enrichment",
+            format!(
+                "{}/{} input, {}/{} control, p-value {:.3e}",
+                enrichment.input_count,
+                enrichment.input_total,
+                enrichment.control_count,
+                enrichment.control_total,
+                enrichment.p_value
+            ),
+        ));
+    }
+    for member in summary.ensemble.iter().flatten() {
+        let strand = if member.reverse_complement {
+            " (reverse complement)"
+        } else {
+            ""
+        };
+        rows.push((
+            "Found by",
+            format!(
+                "{}: {}{}, score {:.3}",
+                member.algorithm, member.consensus, strand, member.score
+            ),
+        ));
+    }
+    if let Some(significance) = &summary.significance {
+        rows.push((
+            "Significance",
+            format!(
+                "p-value {:.3e}, adjusted p-value {:.3e} ({} shuffles)",
+                significance.p_value, significance.adjusted_p_value, significance.shuffles
+            ),
+        ));
+    }
+    writeln!(html, "<table>").unwrap();
+    for (key, value) in rows {
+        writeln!(
+            html,
+            "<tr><th>{key}</th><td>{}</td></tr>",
+            escape_xml(&value)
+        )
+        .unwrap();
+    }
+    writeln!(html, "</table>").unwrap();
+}
+
+/// Draw every sequence as a line with its sites as boxes, above the line on the forward strand and below on the reverse
+fn write_site_map(html: &mut String, sites: &[MapSite], records: &[SequenceRecord]) {
+    let longest = records
+        .iter()
+        .map(|record| record.sequence.len())
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    let scale = MAP_WIDTH / longest as f64;
+    let width = MAP_LABEL_WIDTH + MAP_WIDTH + 10.0;
+    let height = MAP_ROW_HEIGHT * (records.len() + 1) as f64;
+    writeln!(html, "<figure>").unwrap();
+    writeln!(
+        html,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}">"#
+    )
+    .unwrap();
+    for (index, record) in records.iter().enumerate() {
+        let y = MAP_ROW_HEIGHT * (index as f64 + 0.5);
+        writeln!(
+            html,
+            r#"<text x="{x}" y="{y}" font-family="{FONT}" font-size="12" text-anchor="end" dominant-baseline="middle">{id}</text>"#,
+            x = MAP_LABEL_WIDTH - 8.0,
+            id = escape_xml(&record.id)
+        )
+        .unwrap();
+        writeln!(
+            html,
+            r##"<line x1="{MAP_LABEL_WIDTH}" y1="{y}" x2="{x2:.2}" y2="{y}" stroke="#888"/>"##,
+            x2 = MAP_LABEL_WIDTH + record.sequence.len() as f64 * scale
+        )
+        .unwrap();
+        for MapSite {
+            start,
+            end,
+            strand,
+            site,
+            ..
+        } in sites.iter().filter(|site| site.sequence == index)
+        {
+            let (top, color) = match strand {
+                Strand::Forward => (y - MAP_SITE_HEIGHT, FORWARD_COLOR),
+                Strand::Reverse => (y, REVERSE_COLOR),
+            };
+            writeln!(
+                html,
+                r#"<rect x="{x:.2}" y="{top}" width="{site_width:.2}" height="{MAP_SITE_HEIGHT}" fill="{color}"><title>{site} {strand} {start}-{end}</title></rect>"#,
+                x = MAP_LABEL_WIDTH + *start as f64 * scale,
+                // keep short sites on long sequences visible
+                site_width = ((end - start) as f64 * scale).max(2.0),
+                site = escape_xml(site)
+            )
+            .unwrap();
+        }
+    }
+    // scale bar of the longest sequence
+    let y = MAP_ROW_HEIGHT * records.len() as f64 + 4.0;
+    writeln!(
+        html,
+        r#"<line x1="{MAP_LABEL_WIDTH}" y1="{y}" x2="{x2}" y2="{y}" stroke="black"/>"#,
+        x2 = MAP_LABEL_WIDTH + MAP_WIDTH
+    )
+    .unwrap();
+    for (x, label, anchor) in [
+        (MAP_LABEL_WIDTH, "0".to_string(), "start"),
+        (MAP_LABEL_WIDTH + MAP_WIDTH, longest.to_string(), "end"),
+    ] {
+        writeln!(
+            html,
+            r#"<text x="{x}" y="{y}" font-family="{FONT}" font-size="11" text-anchor="{anchor}">{label}</text>"#,
+            y = y + 14.0
+        )
+        .unwrap();
+    }
+    writeln!(html, "</svg>").unwrap();
+    writeln!(
+        html,
+        r#"<figcaption><span style="color: {FORWARD_COLOR}">&#9632;</span> forward strand <span style="color: {REVERSE_COLOR}">&#9632;</span> reverse strand</figcaption>"#
+    )
+    .unwrap();
+    writeln!(html, "</figure>").unwrap();
+}
+
+/// Plot the score of the best run against the iteration as a line
+fn write_convergence_plot(html: &mut String, score_trace: &[f64], label: &str) {
+    let (min, max) = score_trace
+        .iter()
+        .fold((f64::MAX, f64::MIN), |(min, max), &score| {
+            (min.min(score), max.max(score))
+        });
+    // a flat trace is drawn along the middle of the plot
+    let range = if max > min { max - min } else { 1.0 };
+    let steps = score_trace.len().saturating_sub(1).max(1) as f64;
+    let points: Vec<String> = score_trace
+        .iter()
+        .enumerate()
+        .map(|(i, score)| {
+            let x = PLOT_LEFT + PLOT_WIDTH * i as f64 / steps;
+            let y = if max > min {
+                PLOT_MARGIN + PLOT_HEIGHT * (max - score) / range
+            } else {
+                PLOT_MARGIN + PLOT_HEIGHT / 2.0
+            };
+            format!("{x:.2},{y:.2}")
+        })
+        .collect();
+    let width = PLOT_LEFT + PLOT_WIDTH + PLOT_MARGIN;
+    let height = PLOT_HEIGHT + 3.0 * PLOT_MARGIN;
+    let bottom = PLOT_MARGIN + PLOT_HEIGHT;
+    writeln!(html, "<figure>").unwrap();
+    writeln!(
+        html,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}">"#
+    )
+    .unwrap();
+    writeln!(
+        html,
+        r#"<polyline points="{PLOT_LEFT},{PLOT_MARGIN} {PLOT_LEFT},{bottom} {right},{bottom}" fill="none" stroke="black"/>"#,
+        right = PLOT_LEFT + PLOT_WIDTH
+    )
+    .unwrap();
+    for (y, value) in [(PLOT_MARGIN, max), (bottom, min)] {
+        writeln!(
+            html,
+            r#"<text x="{x}" y="{y}" font-family="{FONT}" font-size="11" text-anchor="end" dominant-baseline="middle">{value:.3}</text>"#,
+            x = PLOT_LEFT - 6.0
+        )
+        .unwrap();
+    }
+    writeln!(
+        html,
+        r#"<text x="{x}" y="{y}" font-family="{FONT}" font-size="11" text-anchor="middle">iteration (0 to {iterations})</text>"#,
+        x = PLOT_LEFT + PLOT_WIDTH / 2.0,
+        y = bottom + PLOT_MARGIN,
+        iterations = score_trace.len().saturating_sub(1)
+    )
+    .unwrap();
+    writeln!(
+        html,
+        r#"<polyline points="{}" fill="none" stroke="{FORWARD_COLOR}" stroke-width="2"/>"#,
+        points.join(" ")
+    )
+    .unwrap();
+    writeln!(html, "</svg>").unwrap();
+    writeln!(html, "<figcaption>{}</figcaption>", escape_xml(label)).unwrap();
+    writeln!(html, "</figure>").unwrap();
+}
+
+/// The top motifs by local alignment and the matches in the sequences, when there are any
+fn write_alignments(html: &mut String, summary: &Summary) {
+    if let Some(top_motifs) = &summary.top_motifs {
+        writeln!(html, "<h3>Top alignments</h3>").unwrap();
+        writeln!(html, "<table>").unwrap();
+        writeln!(html, "<tr><th>Rank</th><th>Motif</th><th>Score</th></tr>").unwrap();
+        for (rank, (score, motif)) in top_motifs.iter().enumerate() {
+            writeln!(
+                html,
+                r#"<tr><td>{}</td><td class="sequence">{}</td><td>{}</td></tr>"#,
+                rank + 1,
+                escape_xml(motif),
+                score
+            )
+            .unwrap();
+        }
+        writeln!(html, "</table>").unwrap();
+    }
+    if let Some(hits) = &summary.hits {
+        writeln!(html, "<h3>Matches</h3>").unwrap();
+        writeln!(html, "<table>").unwrap();
+        writeln!(
+            html,
+            "<tr><th>Sequence</th><th>Start</th><th>End</th><th>Distance</th><th>Alignment</th></tr>"
+        )
+        .unwrap();
+        for hit in hits {
+            writeln!(
+                html,
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td><pre>{}</pre></td></tr>",
+                escape_xml(&hit.id),
+                hit.start,
+                hit.end,
+                hit.distance,
+                escape_xml(hit.alignment.trim_end())
+            )
+            .unwrap();
+        }
+        writeln!(html, "</table>").unwrap();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::render_report;
+    use crate::command::{Commands, Summary};
+    use crate::scoring::ScoringMethod;
+    use crate::sequence::{MotifInstance, SequenceRecord, SitePosition, Strand};
+
+    #[test]
+    pub fn test_render_report() {
+        let mut records = SequenceRecord::from_sequences(&["GGTTGACAGG", "TGTCAACC"]);
+        records[1].id = "<seq1>".to_string();
+        let site = |kmer: &str, strand, sequence, offset| MotifInstance {
+            kmer: kmer.to_string(),
+            strand,
+            position: Some(SitePosition { sequence, offset }),
+        };
+        let motifs = vec![
+            site("TTGACA", Strand::Forward, 0, 2),
+            site("TTGACA", Strand::Reverse, 1, 0),
+        ];
+        let summary = Summary {
+            consensus_string: "TTGACA".to_string(),
+            degenerate_consensus: "TTGACA".to_string(),
+            unique_motifs: "TTGACA".to_string(),
+            best_motif: None,
+            best_motif_score: None,
+            top_motifs: Some(vec![(12, "TTGACA".to_string())]),
+            site_posteriors: None,
+            motif_score: Some((ScoringMethod::default(), 1.5)),
+            enrichment: None,
+            significance: None,
+            hits: None,
+            score_trace: Some(vec![4.0, 2.0, 2.0]),
+            ensemble: None,
+        };
+        let header = "MotifFinder 0.1.0\nCommand: Randomized Motif Search\nk: 6\n";
+        let html = render_report(
+            header,
+            &[(motifs, summary)],
+            &records,
+            &Commands::Randomized { num_runs: 5 },
+        )
+        .unwrap();
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        // nothing is fetched when the page is opened
+        assert!(!html.contains("src="));
+        assert!(!html.contains("href="));
+        assert!(html.contains("<tr><th>k</th><td>6</td></tr>"));
+        assert!(html.contains("<h2>Motif 1 of 1: TTGACA</h2>"));
+        assert!(html.contains("&lt;seq1&gt;"));
+        // a logo, a site map and a convergence plot
+        assert_eq!(html.matches("<svg").count(), 3);
+        assert_eq!(html.matches("<rect").count(), 2);
+        assert!(
+            html.contains(r##"<rect x="320.00" y="4" width="480.00" height="8" fill="#255C99">"##)
+        );
+        // the score trace is the thicker line, with one point per score, drawn lower as the score falls
+        let trace = html
+            .split("<polyline ")
+            .skip(1)
+            .find(|polyline| polyline.contains(r#"stroke-width="2""#))
+            .unwrap();
+        let heights: Vec<f64> = trace
+            .split('"')
+            .nth(1)
+            .unwrap()
+            .split_whitespace()
+            .map(|point| point.split(',').nth(1).unwrap().parse().unwrap())
+            .collect();
+        assert_eq!(heights.len(), 3);
+        assert!(heights.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert!(html.contains("<h3>Top alignments</h3>"));
+    }
+}
@@ -0,0 +1,149 @@
+use crate::Error;
+
+// IUPAC code for every set of nucleotides, indexed by a bitmask with A = 1, C = 2, G = 4 and T = 8
+const IUPAC_CODES: [char; 16] = [
+    '-', 'A', 'C', 'M', 'G', 'R', 'S', 'V', 'T', 'W', 'Y', 'H', 'K', 'D', 'B', 'N',
+];
+const NUCLEOTIDES: [char; 4] = ['A', 'C', 'G', 'T'];
+
+/// Frequencies a column needs before it is written with one or two nucleotides in a degenerate consensus
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IupacThresholds {
+    /// a single nucleotide is used when it has at least this frequency and twice the frequency of the next one
+    pub single: f64,
+    /// otherwise a two nucleotide code is used when the two most common nucleotides reach this frequency together
+    pub pair: f64,
+}
+
+impl Default for IupacThresholds {
+    fn default() -> Self {
+        // the rules of Cavener (1987)
+        IupacThresholds {
+            single: 0.5,
+            pair: 0.75,
+        }
+    }
+}
+
+/// IUPAC code of a set of nucleotides, or None if any of them isn't A, C, G or T
+pub fn iupac_code(nucleotides: &[char]) -> Option<char> {
+    let mut mask = 0;
+    for nuc in nucleotides {
+        let index = NUCLEOTIDES.iter().position(|n| n == nuc)?;
+        mask |= 1 << index;
+    }
+    Some(IUPAC_CODES[mask])
+}
+
+/// Nucleotides a IUPAC code stands for, or None if it isn't a IUPAC code
+pub fn iupac_nucleotides(code: char) -> Option<Vec<char>> {
+    let mask = IUPAC_CODES
+        .iter()
+        .skip(1)
+        .position(|&c| c == code.to_ascii_uppercase())?
+        + 1;
+    Some(
+        NUCLEOTIDES
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| mask & (1 << i) != 0)
+            .map(|(_, &nuc)| nuc)
+            .collect(),
+    )
+}
+
+/// ACGT counts of every column of the motifs, where degenerate codes count equally towards each nucleotide they stand for
+pub(crate) fn iupac_counts<S: AsRef<str>>(motifs: &[S], k: usize) -> Vec<[f64; 4]> {
+    let mut counts = vec![[0.0; 4]; k];
+    for motif in motifs {
+        for (column, code) in counts.iter_mut().zip(motif.as_ref().chars()) {
+            let Some(nucleotides) = iupac_nucleotides(code) else {
+                continue;
+            };
+            for nuc in &nucleotides {
+                if let Some(index) = NUCLEOTIDES.iter().position(|n| n == nuc) {
+                    column[index] += 1.0 / nucleotides.len() as f64;
+                }
+            }
+        }
+    }
+    counts
+}
+
+/// Consensus of the motifs where every column is written with the IUPAC code of its most common nucleotides
+///
+/// Columns that meet neither threshold are written with the code of every nucleotide seen in them.
+#[tracing::instrument(skip(motifs))]
+pub fn iupac_consensus<S: AsRef<str>>(
+    motifs: &[S],
+    thresholds: &IupacThresholds,
+) -> Result<String, Error> {
+    let k = motifs
+        .first()
+        .ok_or(Error::NoMotifsFound)?
+        .as_ref()
+        .chars()
+        .count();
+    let counts = iupac_counts(motifs, k);
+    let mut consensus = String::with_capacity(k);
+    for column in counts {
+        let total: f64 = column.iter().sum();
+        if total == 0.0 {
+            consensus.push('N');
+            continue;
+        }
+        let mut column: Vec<(char, f64)> = NUCLEOTIDES
+            .iter()
+            .zip(column)
+            .map(|(&nuc, count)| (nuc, count / total))
+            .collect();
+        column.sort_by(|a, b| b.1.total_cmp(&a.1));
+        let nucleotides: Vec<char> =
+            if column[0].1 >= thresholds.single && column[0].1 >= 2.0 * column[1].1 {
+                vec![column[0].0]
+            } else if column[0].1 + column[1].1 >= thresholds.pair {
+                vec![column[0].0, column[1].0]
+            } else {
+                column
+                    .iter()
+                    .filter(|(_, frequency)| *frequency > 0.0)
+                    .map(|(nuc, _)| *nuc)
+                    .collect()
+            };
+        consensus.push(iupac_code(&nucleotides).ok_or(Error::InvalidNucleotideError)?);
+    }
+    Ok(consensus)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{iupac_code, iupac_consensus, iupac_nucleotides, IupacThresholds};
+
+    #[test]
+    pub fn test_iupac_consensus() {
+        assert_eq!(iupac_code(&['A', 'G']), Some('R'));
+        assert_eq!(iupac_code(&['T', 'C']), Some('Y'));
+        assert_eq!(iupac_code(&['A', 'C', 'G', 'T']), Some('N'));
+        assert_eq!(iupac_code(&['A', 'N']), None);
+        assert_eq!(iupac_nucleotides('W'), Some(vec!['A', 'T']));
+        assert_eq!(iupac_nucleotides('b'), Some(vec!['C', 'G', 'T']));
+        assert_eq!(iupac_nucleotides('X'), None);
+        // degenerate motifs keep their codes
+        let consensus = iupac_consensus(&["TATAWAWR"], &IupacThresholds::default()).unwrap();
+        assert_eq!(consensus, "TATAWAWR");
+        // columns: all T, A or G, mostly A but no T, all four evenly
+        let motifs = ["TAAA", "TGCC", "TAGG", "TGAT"];
+        let consensus = iupac_consensus(&motifs, &IupacThresholds::default()).unwrap();
+        assert_eq!(consensus, "TRAN");
+        let strict = IupacThresholds {
+            single: 1.0,
+            pair: 1.0,
+        };
+        assert_eq!(iupac_consensus(&motifs, &strict).unwrap(), "TRVN");
+        let lenient = IupacThresholds {
+            single: 0.25,
+            pair: 0.5,
+        };
+        assert_eq!(iupac_consensus(&motifs, &lenient).unwrap(), "TRAM");
+    }
+}
@@ -0,0 +1,247 @@
+use std::io::{self, Write};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::alignment::Hit;
+use crate::command::{Commands, GlobalOpts, Summary};
+use crate::discriminative::EnrichedKmer;
+use crate::ensemble::EnsembleMember;
+use crate::motif_io::MotifMatrix;
+use crate::scan::ScanHit;
+use crate::scoring::ScoringMethod;
+use crate::sequence::{MotifInstance, SequenceRecord, Strand};
+use crate::shuffle::Significance;
+use crate::Error;
+
+/// Everything about a run, in the layout of the JSON output file
+#[derive(Debug, Serialize)]
+pub(crate) struct JsonResults<'a> {
+    version: &'static str,
+    parameters: &'a GlobalOpts,
+    command: &'a Commands,
+    /// zero order ACGT frequencies of the background
+    background: [f64; 4],
+    start_time: String,
+    end_time: String,
+    duration_seconds: f64,
+    motifs: Vec<JsonMotif<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scan_hits: Option<&'a [ScanHit]>,
+}
+
+#[derive(Debug, Serialize)]
+struct JsonMotif<'a> {
+    consensus: &'a str,
+    degenerate_consensus: &'a str,
+    unique_motifs: Vec<&'a str>,
+    score: Option<JsonScore>,
+    significance: Option<&'a Significance>,
+    enrichment: Option<&'a EnrichedKmer>,
+    /// the motif of every algorithm that found this one, for the ensemble command
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ensemble: Option<&'a [EnsembleMember]>,
+    /// ACGT counts of the sites, one row per nucleotide
+    counts: Vec<Vec<f64>>,
+    /// ACGT probabilities of the sites, one row per nucleotide
+    profile: Vec<Vec<f64>>,
+    instances: Vec<JsonInstance<'a>>,
+    alignment: Option<JsonAlignment<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+struct JsonScore {
+    method: ScoringMethod,
+    value: f64,
+}
+
+#[derive(Debug, Serialize)]
+struct JsonInstance<'a> {
+    site: &'a str,
+    strand: Strand,
+    /// index of the sequence in the input, with its FASTA id
+    sequence: Option<usize>,
+    id: Option<&'a str>,
+    /// 0-based offset of the site in the forward strand of the sequence
+    offset: Option<usize>,
+    posterior: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+struct JsonAlignment<'a> {
+    best_motif: Option<&'a str>,
+    best_score: Option<isize>,
+    top_motifs: Vec<JsonTopMotif<'a>>,
+    hits: &'a [Hit],
+}
+
+#[derive(Debug, Serialize)]
+struct JsonTopMotif<'a> {
+    motif: &'a str,
+    score: isize,
+}
+
+impl<'a> JsonResults<'a> {
+    pub(crate) fn new(
+        global_opts: &'a GlobalOpts,
+        command: &'a Commands,
+        background: [f64; 4],
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> JsonResults<'a> {
+        let duration_seconds = end
+            .signed_duration_since(start)
+            .num_microseconds()
+            .map_or(0.0, |duration| duration as f64 / 1_000_000.0);
+        JsonResults {
+            version: env!("CARGO_PKG_VERSION"),
+            parameters: global_opts,
+            command,
+            background,
+            start_time: start.to_rfc3339(),
+            end_time: end.to_rfc3339(),
+            duration_seconds,
+            motifs: vec![],
+            scan_hits: None,
+        }
+    }
+
+    pub(crate) fn with_motifs(
+        mut self,
+        results: &'a [(Vec<MotifInstance>, Summary)],
+        records: &'a [SequenceRecord],
+    ) -> Result<JsonResults<'a>, Error> {
+        self.motifs = results
+            .iter()
+            .map(|(motifs, summary)| json_motif(motifs, summary, records))
+            .collect::<Result<Vec<JsonMotif>, Error>>()?;
+        Ok(self)
+    }
+
+    pub(crate) fn with_scan_hits(mut self, hits: &'a [ScanHit]) -> JsonResults<'a> {
+        self.scan_hits = Some(hits);
+        self
+    }
+
+    pub(crate) fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        serde_json::to_writer_pretty(&mut *writer, self)?;
+        writeln!(writer)
+    }
+}
+
+fn json_motif<'a>(
+    motifs: &'a [MotifInstance],
+    summary: &'a Summary,
+    records: &'a [SequenceRecord],
+) -> Result<JsonMotif<'a>, Error> {
+    let matrix = MotifMatrix::from_sites(&summary.degenerate_consensus, motifs)?;
+    let instances = motifs
+        .iter()
+        .enumerate()
+        .map(|(i, motif)| JsonInstance {
+            site: &motif.kmer,
+            strand: motif.strand,
+            sequence: motif.position.map(|position| position.sequence),
+            id: motif
+                .position
+                .and_then(|position| records.get(position.sequence))
+                .map(|record| record.id.as_str()),
+            offset: motif.position.map(|position| position.offset),
+            posterior: summary
+                .site_posteriors
+                .as_ref()
+                .and_then(|posteriors| posteriors.get(i).copied()),
+        })
+        .collect();
+    let alignment = summary.hits.as_deref().map(|hits| JsonAlignment {
+        best_motif: summary.best_motif.as_deref(),
+        best_score: summary.best_motif_score,
+        top_motifs: summary
+            .top_motifs
+            .iter()
+            .flatten()
+            .map(|(score, motif)| JsonTopMotif {
+                motif,
+                score: *score,
+            })
+            .collect(),
+        hits,
+    });
+    Ok(JsonMotif {
+        consensus: &summary.consensus_string,
+        degenerate_consensus: &summary.degenerate_consensus,
+        unique_motifs: summary.unique_motifs.split_whitespace().collect(),
+        score: summary
+            .motif_score
+            .map(|(method, value)| JsonScore { method, value }),
+        significance: summary.significance.as_ref(),
+        enrichment: summary.enrichment.as_ref(),
+        ensemble: summary.ensemble.as_deref(),
+        profile: matrix.frequencies(),
+        counts: matrix.rows,
+        instances,
+        alignment,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::json_motif;
+    use crate::command::Summary;
+    use crate::sequence::{MotifInstance, SequenceRecord, SitePosition, Strand};
+
+    #[test]
+    pub fn test_json_motif() {
+        let records = SequenceRecord::from_sequences(&["GGTTGACAGG"]);
+        let motifs = vec![
+            MotifInstance {
+                kmer: "TTGACA".to_string(),
+                strand: Strand::Forward,
+                position: Some(SitePosition {
+                    sequence: 0,
+                    offset: 2,
+                }),
+            },
+            MotifInstance::new("TTGACT".to_string(), Strand::Reverse),
+        ];
+        let summary = Summary {
+            consensus_string: "TTGACA".to_string(),
+            degenerate_consensus: "TTGACW".to_string(),
+            unique_motifs: "TTGACA TTGACT".to_string(),
+            best_motif: None,
+            best_motif_score: None,
+            top_motifs: None,
+            site_posteriors: Some(vec![0.9, 0.4]),
+            motif_score: None,
+            enrichment: None,
+            significance: None,
+            hits: None,
+            score_trace: None,
+            ensemble: None,
+        };
+        let motif = json_motif(&motifs, &summary, &records).unwrap();
+        let json = serde_json::to_value(&motif).unwrap();
+        assert_eq!(
+            json["unique_motifs"],
+            serde_json::json!(["TTGACA", "TTGACT"])
+        );
+        assert_eq!(
+            json["counts"][0],
+            serde_json::json!([0.0, 0.0, 0.0, 2.0, 0.0, 1.0])
+        );
+        assert_eq!(json["profile"][3][5], 0.5);
+        assert_eq!(
+            json["instances"][0],
+            serde_json::json!({
+                "site": "TTGACA",
+                "strand": "+",
+                "sequence": 0,
+                "id": "seq0",
+                "offset": 2,
+                "posterior": 0.9
+            })
+        );
+        assert_eq!(json["instances"][1]["id"], serde_json::Value::Null);
+        assert_eq!(json["alignment"], serde_json::Value::Null);
+    }
+}
@@ -1,27 +1,75 @@
+mod algorithm;
 mod alignment;
+mod annotation;
+mod background;
 mod bwt;
 mod command;
+mod discriminative;
+mod ensemble;
+mod expectation_maximization;
 mod gibbs_sampler;
+mod html;
+mod iupac;
+mod json;
+mod logo;
 mod median_string;
+mod meme;
+mod motif;
+mod motif_io;
+mod progress;
 mod randomized_motif_search;
+mod scan;
+mod scoring;
+mod sequence;
+mod shuffle;
+mod site_model;
 mod utils;
 
+pub use algorithm::{
+    run_algorithms, DiscriminativeMotifSearch, ExpectationMaximization, GibbsSampler, MedianString,
+    MotifAlgorithm, MotifResult, PatternSearch, RandomizedMotifSearch, SearchConfig,
+};
 use alignment::local_alignment;
+pub use alignment::{align_motifs_distance, Hit};
+pub use annotation::{
+    genomic_offset, hit_annotations, instance_annotations, scan_annotations, write_bed, write_gff3,
+    Annotation, OutputFormat,
+};
+use discriminative::discriminative_motif_search;
+use expectation_maximization::iterate_expectation_maximization;
 use gibbs_sampler::iterate_gibbs_sampler;
-use indicatif::{MultiProgress, ParallelProgressIterator, ProgressBar, ProgressStyle};
-use median_string::median_string;
+use median_string::{closest_sites, median_string};
+use rand::{rngs::StdRng, SeedableRng};
 use randomized_motif_search::iterate_randomized_motif_search;
 use rayon::prelude::*;
 use std::str;
 use std::{
     collections::{HashMap, HashSet},
     fs::File,
+    sync::Arc,
 };
 use tracing::{error, info, trace};
 
+pub use background::Background;
 use bio::io::fasta;
 #[doc(hidden)]
 pub use command::MotifFinder;
+pub use discriminative::EnrichedKmer;
+pub use ensemble::{ensemble_motifs, EnsembleMember, EnsembleMotif};
+pub use expectation_maximization::EmResult;
+pub use iupac::{iupac_code, iupac_consensus, iupac_nucleotides, IupacThresholds};
+pub use logo::{information_content, render_logo};
+pub use meme::{write_meme, MemeMotif};
+pub use motif::{Alphabet, CountMatrix, Motif, MotifSet, Profile};
+pub use motif_io::{
+    parse_matrices, read_matrices, write_matrices, MatrixValues, MotifFormat, MotifMatrix,
+};
+pub use progress::{NoProgress, ProgressReporter};
+pub use scan::{scan_sequences, PositionWeightMatrix, ScanHit, ScanThreshold};
+pub use scoring::ScoringMethod;
+pub use sequence::{reverse_complement, MotifInstance, SequenceRecord, SitePosition, Strand};
+pub use shuffle::{dinucleotide_shuffle, Significance};
+pub use site_model::SiteModel;
 
 #[derive(Debug)]
 pub enum Error {
@@ -40,19 +88,83 @@ pub enum Error {
     InvalidNumberMotifs,
 }
 
+/// Options shared by the de novo motif search algorithms
+#[derive(Debug, Clone, Default)]
+pub struct SearchOptions {
+    /// also consider kmers on the reverse complement strand
+    pub both_strands: bool,
+    /// how many motif occurrences each sequence is assumed to contain
+    /// (honoured by the Gibbs Sampler and Randomized Motif Search)
+    pub site_model: SiteModel,
+    /// score used to rank motif sets within and between runs
+    /// (with a site model other than OOPS sets are ranked by likelihood, so only the default can be used)
+    pub scoring: ScoringMethod,
+    /// nucleotide model that kmers are ranked against with log-odds
+    pub background: Background,
+    /// seed of the random number generators of the runs, for reproducible results
+    /// (every run draws a fresh seed when there is none)
+    pub seed: Option<u64>,
+    /// where the searches report their progress, nowhere when there is no reporter
+    pub progress: Option<Arc<dyn ProgressReporter>>,
+}
+
+impl SearchOptions {
+    pub fn progress(&self) -> &dyn ProgressReporter {
+        self.progress.as_deref().unwrap_or(&NoProgress)
+    }
+}
+
+/// Random number generator of one run of a search
+///
+/// With a seed, the generator only depends on the seed and the index of the run, so results don't change with
+/// the order rayon happens to schedule the runs in.
+pub(crate) fn run_rng(seed: Option<u64>, run: usize) -> StdRng {
+    match seed {
+        // spread the run indices so consecutive seeds don't share runs
+        Some(seed) => {
+            StdRng::seed_from_u64(seed ^ (run as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15))
+        }
+        None => StdRng::from_entropy(),
+    }
+}
+
+/// Sites found by one of the sampling searches, from the best of its runs
+#[derive(Debug, Clone)]
+pub struct SearchResult {
+    /// the sites of the best run
+    pub motifs: Vec<MotifInstance>,
+    /// score of the best run at its random start and after every iteration, lower is better
+    pub score_trace: Vec<f64>,
+}
+
+/// Check that the sampling searches can rank motif sets by the chosen score
+///
+/// A site model other than OOPS keeps a varying number of sites, which only a likelihood can compare fairly: a
+/// set of one site would otherwise have a perfect score.
+fn check_scoring(options: &SearchOptions) -> Result<(), Error> {
+    if options.site_model != SiteModel::Oops && options.scoring != ScoringMethod::default() {
+        error!(
+            "{} scoring can't rank motifs with the {} site model, which ranks them by likelihood",
+            options.scoring, options.site_model
+        );
+        return Err(Error::InvalidInputError);
+    }
+    Ok(())
+}
+
 #[tracing::instrument(skip_all)]
-fn scoring_function(motif_matrix: &[String]) -> usize {
+fn scoring_function<S: AsRef<str>>(motif_matrix: &[S]) -> usize {
     // given a motif matrix, generate its score by finding the highest count of nucleotide in a given position
     // and subtract that count from the total length of the column
     let mut score = 0;
-    let k = motif_matrix.get(0).unwrap().chars().count();
+    let k = motif_matrix.first().unwrap().as_ref().chars().count();
     let motifs_length = motif_matrix.len();
     trace!(motifs_length);
     // println!("len {}",motifs_length);
     for i in 0..k {
         let mut count: HashMap<char, usize> = HashMap::new();
         for motif in motif_matrix {
-            if let Some(nuc) = motif.chars().nth(i) {
+            if let Some(nuc) = motif.as_ref().chars().nth(i) {
                 *count.entry(nuc).or_insert(0) += 1;
             } else {
                 continue;
@@ -67,18 +179,20 @@ fn scoring_function(motif_matrix: &[String]) -> usize {
 }
 
 #[tracing::instrument(skip_all)]
-fn generate_profile_given_motif_matrix(
-    motif_matrix: &[String],
+fn generate_profile_given_motif_matrix<S: AsRef<str>>(
+    motif_matrix: &[S],
     pseudo: bool,
 ) -> Result<Vec<Vec<f64>>, Error> {
     // generate probabilities per column using the count matrix divided by sum of each column
-    let k = motif_matrix[0].len();
+    let k = motif_matrix[0].as_ref().len();
     trace!(k);
     let count_matrix = generate_count_matrix(motif_matrix, k, pseudo);
     let mut profile_matrix: Vec<Vec<f64>> = vec![vec![0.0; k]; 4];
-    let sum = motif_matrix.len() as f64;
     // iterating over each position
     for i in 0..k {
+        // divide by the pseudocounted column sum so every column is a distribution
+        let sum: usize = count_matrix.iter().map(|row| row[i]).sum();
+        let sum = sum as f64;
         // iterating over each nucleotide base
         for j in 0..4 {
             // print_vector_space_delimited(row.clone());
@@ -97,7 +211,11 @@ fn generate_profile_given_motif_matrix(
 }
 
 #[tracing::instrument(skip_all)]
-fn generate_count_matrix(motif_matrix: &[String], k: usize, pseudo: bool) -> Vec<Vec<usize>> {
+fn generate_count_matrix<S: AsRef<str>>(
+    motif_matrix: &[S],
+    k: usize,
+    pseudo: bool,
+) -> Vec<Vec<usize>> {
     // enumerate motif matrix per nucleotide per position
     let mut val = 0;
     if pseudo {
@@ -106,7 +224,7 @@ fn generate_count_matrix(motif_matrix: &[String], k: usize, pseudo: bool) -> Vec
     let mut count_matrix: Vec<Vec<usize>> = vec![vec![val; k]; 4]; // ACGT = 4
     for i in 0..k {
         for motif in motif_matrix {
-            if let Some(index) = match motif.chars().nth(i) {
+            if let Some(index) = match motif.as_ref().chars().nth(i) {
                 Some('A') => Some(0),
                 Some('C') => Some(1),
                 Some('G') => Some(2),
@@ -144,7 +262,7 @@ fn generate_probability(kmer: &str, profile: &[Vec<f64>]) -> f64 {
 }
 
 #[tracing::instrument(skip_all)]
-fn consensus_string(motifs: &[String], k: usize) -> Result<String, Error> {
+fn consensus_string<S: AsRef<str>>(motifs: &[S], k: usize) -> Result<String, Error> {
     let mut consensus = String::new();
     let count_matrix = generate_count_matrix(motifs, k, true);
     for i in 0..k {
@@ -176,42 +294,20 @@ fn consensus_string(motifs: &[String], k: usize) -> Result<String, Error> {
 pub fn align_motifs_multi_threaded(
     sequences: &[String],
     motifs: &[String],
+    progress: &dyn ProgressReporter,
 ) -> Result<Vec<(isize, String)>, Error> {
-    let motifs_len = motifs.len();
-    let sequences_len = sequences.len();
-    let pb = ProgressBar::new(
-        motifs_len
-            .try_into()
-            .map_err(|_| Error::InvalidNumberMotifs)?,
+    progress.start(
+        &format!(
+            "Aligning {} unique motifs to {} sequences",
+            motifs.len(),
+            sequences.len()
+        ),
+        motifs.len(),
     );
-    let sty =
-        ProgressStyle::with_template(&format!("{{prefix:.bold}}▕{{bar:.{}}}▏{{msg}} ", "9.on_0"))
-            .unwrap()
-            .progress_chars("█▉▊▋▌▍▎▏  ");
-    pb.set_style(
-        ProgressStyle::with_template(
-            "[{elapsed_precise}] {spinner:.9.on_0} {bar:50.9.on_0} {pos:>2}/{len:2} {msg} ({eta})",
-        )
-        .unwrap(),
-    );
-    pb.reset_eta();
-
-    let m = MultiProgress::new();
-
-    let total_pb = m.add(pb);
-    total_pb.println(format!(
-        "Aligning {} unique motifs to {} sequences",
-        motifs_len,
-        sequences.len()
-    ));
 
     let mut top_five: Vec<(isize, String)> = motifs
         .par_iter()
-        .progress_with(total_pb.clone())
         .map(|motif| {
-            let inner = m.add(ProgressBar::new(sequences_len.try_into().unwrap()));
-            inner.set_style(sty.clone());
-            inner.set_prefix(motif.to_string());
             let mut total_score = 0;
             let mut highest_score = 0;
             let mut best_motif = String::from("");
@@ -222,14 +318,13 @@ pub fn align_motifs_multi_threaded(
                     best_motif = w_align;
                 }
                 total_score += score;
-                inner.inc(1);
             }
-            inner.finish_and_clear();
+            progress.advance(1);
             Ok((total_score, best_motif))
         })
         .collect::<Result<Vec<(isize, String)>, Error>>()?;
 
-    total_pb.finish_with_message("Done!");
+    progress.finish("Done!");
     top_five.par_sort_by(|a, b| b.0.cmp(&a.0));
     top_five.dedup();
     top_five.truncate(5);
@@ -237,16 +332,16 @@ pub fn align_motifs_multi_threaded(
 }
 
 #[tracing::instrument]
-pub fn load_data(path_to_file: &str, num_entries: usize) -> Result<Vec<String>, Error> {
+pub fn load_records(path_to_file: &str, num_entries: usize) -> Result<Vec<SequenceRecord>, Error> {
     info!("Loading data from '{}'...", path_to_file);
-    let mut sequences = vec![];
+    let mut records = vec![];
     let file = match File::open(path_to_file) {
         Ok(file) => file,
         Err(_) => return Err(Error::FileNotFoundError(path_to_file.to_string())),
     };
-    let mut records = fasta::Reader::new(file).records();
+    let mut fasta_records = fasta::Reader::new(file).records();
     let mut count = 0;
-    while let Some(Ok(record)) = records.next() {
+    while let Some(Ok(record)) = fasta_records.next() {
         count += 1;
         if count > num_entries {
             break;
@@ -258,67 +353,186 @@ pub fn load_data(path_to_file: &str, num_entries: usize) -> Result<Vec<String>,
         .to_string()
         .to_uppercase();
 
-        sequences.push(s);
+        records.push(SequenceRecord {
+            id: record.id().to_string(),
+            description: record.desc().map(|desc| desc.to_string()),
+            sequence: s,
+        });
     }
-    info!("Done loading data: {} entries", sequences.len());
-    Ok(sequences)
+    info!("Done loading data: {} entries", records.len());
+    Ok(records)
+}
+
+#[tracing::instrument]
+pub fn load_data(path_to_file: &str, num_entries: usize) -> Result<Vec<String>, Error> {
+    Ok(load_records(path_to_file, num_entries)?
+        .into_iter()
+        .map(|record| record.sequence)
+        .collect())
 }
 
-#[tracing::instrument(skip(sequences))]
+#[tracing::instrument(skip(sequences, options))]
 pub fn run_gibbs_sampler(
-    sequences: &Vec<String>,
+    sequences: &[String],
     k: usize,
     num_runs: usize,
     num_iterations: usize,
-) -> Result<Vec<String>, Error> {
+    options: &SearchOptions,
+) -> Result<SearchResult, Error> {
     if num_runs == 0 {
         return Err(Error::InvalidNumberOfRuns);
     }
     if num_iterations == 0 {
         return Err(Error::InvalidNumberOfIterations);
     }
+    check_scoring(options)?;
 
-    iterate_gibbs_sampler(sequences, k, sequences.len(), num_iterations, num_runs)
+    iterate_gibbs_sampler(
+        sequences,
+        k,
+        sequences.len(),
+        num_iterations,
+        num_runs,
+        options,
+    )
 }
 
-#[tracing::instrument(skip(sequences))]
-pub fn run_median_string(sequences: &[String], k: usize) -> Result<Vec<String>, Error> {
-    let median_string = median_string(k, sequences)?;
+#[tracing::instrument(skip(sequences, options))]
+pub fn run_median_string(
+    sequences: &[String],
+    k: usize,
+    options: &SearchOptions,
+) -> Result<Vec<MotifInstance>, Error> {
+    let median_string = median_string(k, sequences, options.both_strands, options.progress())?;
     info!("Median string: {}", median_string);
-    let vec = vec![median_string];
-    Ok(vec)
+    // report where the median string matches best rather than the string itself, so its sites have a strand and position
+    let sites = closest_sites(&median_string, sequences, options.both_strands);
+    if sites.is_empty() {
+        return Err(Error::NoMotifsFound);
+    }
+    Ok(sites)
 }
 
-#[tracing::instrument(skip(sequences))]
+#[tracing::instrument(skip(sequences, options))]
 pub fn run_randomized_motif_search(
     sequences: &[String],
     k: usize,
     num_runs: usize,
-) -> Result<Vec<String>, Error> {
+    options: &SearchOptions,
+) -> Result<SearchResult, Error> {
+    if num_runs == 0 {
+        return Err(Error::InvalidNumberOfRuns);
+    }
+    check_scoring(options)?;
+    iterate_randomized_motif_search(sequences, k, num_runs, options)
+}
+
+#[tracing::instrument(skip(sequences, options))]
+pub fn run_expectation_maximization(
+    sequences: &[String],
+    k: usize,
+    num_runs: usize,
+    num_iterations: usize,
+    options: &SearchOptions,
+) -> Result<EmResult, Error> {
     if num_runs == 0 {
         return Err(Error::InvalidNumberOfRuns);
     }
-    iterate_randomized_motif_search(sequences, k, num_runs)
+    if num_iterations == 0 {
+        return Err(Error::InvalidNumberOfIterations);
+    }
+    iterate_expectation_maximization(sequences, k, num_iterations, num_runs, options)
+}
+
+#[tracing::instrument(skip(sequences, control, options))]
+pub fn run_discriminative_motif_search(
+    sequences: &[String],
+    control: &[String],
+    k: usize,
+    options: &SearchOptions,
+) -> Result<(Vec<MotifInstance>, EnrichedKmer), Error> {
+    if sequences.is_empty() {
+        return Err(Error::InvalidInputError);
+    }
+    discriminative_motif_search(sequences, control, k, options)
+}
+
+#[tracing::instrument(skip(sequences, options))]
+pub fn run_scan(
+    sequences: &[SequenceRecord],
+    matrix_file: &str,
+    format: Option<MotifFormat>,
+    threshold: ScanThreshold,
+    options: &SearchOptions,
+) -> Result<Vec<ScanHit>, Error> {
+    // the log-odds scores and p-values are against the zero order background
+    let background = options.background.frequencies();
+    let mut hits = vec![];
+    for motif in read_matrices(matrix_file, format)? {
+        let matrix =
+            PositionWeightMatrix::new(&motif.name, &motif.columns(), motif.values, background)?;
+        info!("Scanning for {} ({} columns)", motif.name, matrix.len());
+        hits.extend(scan_sequences(
+            sequences,
+            &matrix,
+            threshold,
+            options.both_strands,
+        ));
+    }
+    Ok(hits)
 }
 
 #[tracing::instrument(skip(motifs))]
-pub fn generate_consensus_string(motifs: &[String], k: usize) -> Result<String, Error> {
+pub fn generate_consensus_string<S: AsRef<str>>(motifs: &[S], k: usize) -> Result<String, Error> {
     if motifs.is_empty() {
         return Err(Error::NoMotifsFound);
     } else if motifs.len() == 1 {
-        return Ok(motifs[0].clone());
+        return Ok(motifs[0].as_ref().to_string());
     }
     consensus_string(motifs, k)
 }
 
 #[tracing::instrument(skip(motifs))]
-pub fn unique_motifs(motifs: &[String]) -> HashSet<String> {
-    motifs.into_par_iter().cloned().collect::<HashSet<String>>()
+pub fn unique_motifs<S: AsRef<str> + Sync>(motifs: &[S]) -> HashSet<String> {
+    motifs
+        .into_par_iter()
+        .map(|motif| motif.as_ref().to_string())
+        .collect::<HashSet<String>>()
+}
+
+/// Replace the motif sites in the sequences with N so they can't be found again
+///
+/// Only the sites themselves are masked, at their position, rather than every occurrence of their kmers: at small k a
+/// frequent kmer, such as a stretch of low-complexity sequence, would otherwise wipe out unrelated regions before the
+/// next search. A site on the reverse strand covers the same nucleotides of the forward strand. Instances without a
+/// position weren't taken from the sequences and are left alone.
+#[tracing::instrument(skip_all)]
+pub fn mask_motif_sites(sequences: &[String], motifs: &[MotifInstance]) -> Vec<String> {
+    let mut masked: Vec<Vec<u8>> = sequences
+        .iter()
+        .map(|sequence| sequence.as_bytes().to_vec())
+        .collect();
+    for motif in motifs {
+        let Some(position) = motif.position else {
+            continue;
+        };
+        if let Some(sequence) = masked.get_mut(position.sequence) {
+            let end = (position.offset + motif.kmer.len()).min(sequence.len());
+            if position.offset < end {
+                sequence[position.offset..end].fill(b'N');
+            }
+        }
+    }
+    masked
+        .into_iter()
+        .map(|sequence| String::from_utf8(sequence).unwrap())
+        .collect()
 }
 
 #[cfg(test)]
 mod test {
     use crate::align_motifs_multi_threaded;
+    use crate::{MotifInstance, SitePosition, Strand};
 
     #[test]
     pub fn test_load_data() {
@@ -336,17 +550,154 @@ mod test {
         assert_eq!(sequences.len(), 0);
     }
 
+    #[test]
+    pub fn test_load_records() {
+        let records = super::load_records("promoters.fasta", 2).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].id, "AC_pNR");
+        assert_eq!(records[0].description.as_deref(), Some("[1979-2664]"));
+        let sequences = super::load_data("promoters.fasta", 2).unwrap();
+        assert_eq!(records[1].sequence, sequences[1]);
+    }
+
     #[test]
     pub fn test_entries_less_than_five() {
         let sequences = super::load_data("promoters.fasta", 4).unwrap();
-        let motifs = super::run_randomized_motif_search(&sequences, 8, 20).unwrap();
-        let top_five = align_motifs_multi_threaded(&sequences, &motifs).unwrap();
+        let motifs = super::run_randomized_motif_search(&sequences, 8, 20, &Default::default())
+            .unwrap()
+            .motifs;
+        let motifs: Vec<String> = motifs.into_iter().map(|motif| motif.kmer).collect();
+        let top_five =
+            align_motifs_multi_threaded(&sequences, &motifs, &super::NoProgress).unwrap();
         assert!(top_five.len() <= 4);
         let sequences = super::load_data("promoters.fasta", 2).unwrap();
         assert_eq!(sequences.len(), 2);
-        let motifs = super::run_randomized_motif_search(&sequences, 8, 20).unwrap();
+        let motifs = super::run_randomized_motif_search(&sequences, 8, 20, &Default::default())
+            .unwrap()
+            .motifs;
         assert_eq!(motifs.len(), 2);
-        let top_five = align_motifs_multi_threaded(&sequences, &motifs).unwrap();
+        let motifs: Vec<String> = motifs.into_iter().map(|motif| motif.kmer).collect();
+        let top_five =
+            align_motifs_multi_threaded(&sequences, &motifs, &super::NoProgress).unwrap();
         assert!(top_five.len() <= 2);
     }
+
+    #[test]
+    pub fn test_score_trace() {
+        let sequences = super::load_data("promoters.fasta", 4).unwrap();
+        let result =
+            super::run_randomized_motif_search(&sequences, 8, 20, &Default::default()).unwrap();
+        // a run only moves on to better motifs
+        assert!(!result.score_trace.is_empty());
+        assert!(result
+            .score_trace
+            .windows(2)
+            .all(|scores| scores[1] < scores[0]));
+    }
+
+    #[test]
+    pub fn test_both_strands() {
+        let sequences = super::load_data("promoters.fasta", 4).unwrap();
+        let options = super::SearchOptions {
+            both_strands: true,
+            ..Default::default()
+        };
+        let motifs = super::run_gibbs_sampler(&sequences, 8, 5, 50, &options)
+            .unwrap()
+            .motifs;
+        assert_eq!(motifs.len(), 4);
+        for motif in &motifs {
+            let found = match motif.strand {
+                super::Strand::Forward => motif.kmer.clone(),
+                super::Strand::Reverse => super::reverse_complement(&motif.kmer),
+            };
+            assert!(sequences.iter().any(|sequence| sequence.contains(&found)));
+        }
+    }
+
+    #[test]
+    pub fn test_scoring_with_site_model() {
+        let sequences = super::load_data("promoters.fasta", 4).unwrap();
+        let options = super::SearchOptions {
+            site_model: super::SiteModel::Zoops,
+            scoring: super::ScoringMethod::Entropy,
+            ..Default::default()
+        };
+        assert!(super::run_gibbs_sampler(&sequences, 8, 5, 50, &options).is_err());
+        assert!(super::run_randomized_motif_search(&sequences, 8, 5, &options).is_err());
+        let options = super::SearchOptions {
+            scoring: super::ScoringMethod::Entropy,
+            ..Default::default()
+        };
+        assert!(super::run_randomized_motif_search(&sequences, 8, 5, &options).is_ok());
+    }
+
+    #[test]
+    pub fn test_seed() {
+        let sequences = super::load_data("promoters.fasta", 4).unwrap();
+        let options = super::SearchOptions {
+            seed: Some(7),
+            ..Default::default()
+        };
+        let first = super::run_gibbs_sampler(&sequences, 8, 5, 50, &options).unwrap();
+        let second = super::run_gibbs_sampler(&sequences, 8, 5, 50, &options).unwrap();
+        assert_eq!(first.motifs, second.motifs);
+        assert_eq!(first.score_trace, second.score_trace);
+        let first = super::run_randomized_motif_search(&sequences, 8, 20, &options).unwrap();
+        let second = super::run_randomized_motif_search(&sequences, 8, 20, &options).unwrap();
+        assert_eq!(first.motifs, second.motifs);
+    }
+
+    #[test]
+    pub fn test_profile_columns_sum_to_one() {
+        let motifs = ["ACGT", "ACGA"];
+        let counts = super::generate_count_matrix(&motifs, 4, true);
+        let column: usize = counts.iter().map(|row| row[0]).sum();
+        // a pseudocount for each nucleotide on top of the two motifs, so dividing by the number of motifs gave 3
+        assert_eq!(column, 6);
+        let profile = super::generate_profile_given_motif_matrix(&motifs, true).unwrap();
+        for i in 0..4 {
+            let sum: f64 = profile.iter().map(|row| row[i]).sum();
+            assert!((sum - 1.0).abs() < 1e-9);
+        }
+        assert!((profile[0][0] - 0.5).abs() < 1e-9);
+        assert!((profile[3][3] - 2.0 / 6.0).abs() < 1e-9);
+    }
+
+    fn site(kmer: &str, strand: Strand, sequence: usize, offset: usize) -> MotifInstance {
+        MotifInstance {
+            kmer: kmer.to_string(),
+            strand,
+            position: Some(SitePosition { sequence, offset }),
+        }
+    }
+
+    #[test]
+    pub fn test_mask_motif_sites() {
+        let sequences = vec!["ACGTTTGACGGA".to_string(), "CCGTCAAACC".to_string()];
+        let motifs = vec![site("TTGAC", Strand::Forward, 0, 4)];
+        let masked = super::mask_motif_sites(&sequences, &motifs);
+        assert_eq!(masked, vec!["ACGTNNNNNGGA", "CCGTCAAACC"]);
+    }
+
+    #[test]
+    pub fn test_mask_reverse_strand_site() {
+        let sequences = vec!["ACGTTTGACGGA".to_string(), "CCGTCAAACC".to_string()];
+        // GTCAA is the reverse complement of TTGAC
+        let motifs = vec![site("TTGAC", Strand::Reverse, 1, 2)];
+        let masked = super::mask_motif_sites(&sequences, &motifs);
+        assert_eq!(masked, vec!["ACGTTTGACGGA", "CCNNNNNACC"]);
+    }
+
+    #[test]
+    pub fn test_mask_only_found_sites() {
+        // the kmer of the site occurs twice more, in the same sequence and in another, but only the site is masked
+        let sequences = vec!["AAAACGTAAAA".to_string(), "CGTCGT".to_string()];
+        let motifs = vec![
+            site("AAAA", Strand::Forward, 0, 0),
+            MotifInstance::new("CGT".to_string(), Strand::Forward),
+        ];
+        let masked = super::mask_motif_sites(&sequences, &motifs);
+        assert_eq!(masked, vec!["NNNNCGTAAAA", "CGTCGT"]);
+    }
 }
@@ -0,0 +1,148 @@
+use std::fmt::Write;
+
+const NUCLEOTIDES: [char; 4] = ['A', 'C', 'G', 'T'];
+const COLORS: [&str; 4] = ["#109648", "#255C99", "#F7B32B", "#D62839"];
+
+const COLUMN_WIDTH: f64 = 40.0;
+// height of 2 bits, the most a column of DNA can hold
+const STACK_HEIGHT: f64 = 200.0;
+const LEFT_MARGIN: f64 = 50.0;
+const TOP_MARGIN: f64 = 30.0;
+const BOTTOM_MARGIN: f64 = 30.0;
+const FONT: &str = "Arial, Helvetica, sans-serif";
+// height of a capital letter at a font size of 100, used to stretch letters to their height in the stack
+const CAP_HEIGHT: f64 = 71.6;
+
+/// Information content of every column of an ACGT profile in bits, against an even background
+pub fn information_content(profile: &[Vec<f64>]) -> Vec<f64> {
+    let k = profile.first().map_or(0, |row| row.len());
+    (0..k)
+        .map(|i| {
+            let entropy: f64 = profile
+                .iter()
+                .map(|row| row[i])
+                .filter(|&p| p > 0.0)
+                .map(|p| -p * p.log2())
+                .sum();
+            (2.0 - entropy).max(0.0)
+        })
+        .collect()
+}
+
+/// Draw the profile as a sequence logo in SVG
+///
+/// Every column is a stack of its nucleotides as tall as the information content of the column, with each
+/// nucleotide taking a share of the stack equal to its probability and the most likely nucleotide on top.
+pub fn render_logo(name: &str, profile: &[Vec<f64>]) -> String {
+    let information = information_content(profile);
+    let width = LEFT_MARGIN + COLUMN_WIDTH * information.len() as f64 + 10.0;
+    let height = TOP_MARGIN + STACK_HEIGHT + BOTTOM_MARGIN;
+    let baseline = TOP_MARGIN + STACK_HEIGHT;
+    // writing to a String can't fail
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}">"#
+    )
+    .unwrap();
+    writeln!(svg, "<title>{}</title>", escape_xml(name)).unwrap();
+    writeln!(
+        svg,
+        r#"<text x="{LEFT_MARGIN}" y="18" font-family="{FONT}" font-size="14">{}</text>"#,
+        escape_xml(name)
+    )
+    .unwrap();
+    // y axis in bits
+    writeln!(
+        svg,
+        r#"<line x1="{x}" y1="{TOP_MARGIN}" x2="{x}" y2="{baseline}" stroke="black"/>"#,
+        x = LEFT_MARGIN - 5.0
+    )
+    .unwrap();
+    for bits in 0..=2 {
+        let y = baseline - STACK_HEIGHT * bits as f64 / 2.0;
+        writeln!(
+            svg,
+            r#"<line x1="{x1}" y1="{y}" x2="{x2}" y2="{y}" stroke="black"/>"#,
+            x1 = LEFT_MARGIN - 10.0,
+            x2 = LEFT_MARGIN - 5.0
+        )
+        .unwrap();
+        writeln!(
+            svg,
+            r#"<text x="{x}" y="{y}" font-family="{FONT}" font-size="12" text-anchor="end" dominant-baseline="middle">{bits}</text>"#,
+            x = LEFT_MARGIN - 12.0
+        )
+        .unwrap();
+    }
+    writeln!(
+        svg,
+        r#"<text x="12" y="{y}" font-family="{FONT}" font-size="12" text-anchor="middle" transform="rotate(-90 12 {y})">bits</text>"#,
+        y = TOP_MARGIN + STACK_HEIGHT / 2.0
+    )
+    .unwrap();
+    for (i, bits) in information.iter().enumerate() {
+        let x = LEFT_MARGIN + COLUMN_WIDTH * i as f64;
+        let mut letters: Vec<(usize, f64)> = profile.iter().map(|row| row[i]).enumerate().collect();
+        // drawn from the bottom up, so the most likely nucleotide ends up on top
+        letters.sort_by(|a, b| a.1.total_cmp(&b.1));
+        let mut bottom = baseline;
+        for (nuc, probability) in letters {
+            let letter_height = probability * bits / 2.0 * STACK_HEIGHT;
+            if letter_height < 0.5 {
+                continue;
+            }
+            writeln!(
+                svg,
+                r#"<text transform="translate({x:.2} {bottom:.2}) scale(1 {scale:.4})" font-family="{FONT}" font-weight="bold" font-size="100" textLength="{COLUMN_WIDTH}" lengthAdjust="spacingAndGlyphs" fill="{color}">{letter}</text>"#,
+                scale = letter_height / CAP_HEIGHT,
+                color = COLORS[nuc],
+                letter = NUCLEOTIDES[nuc]
+            )
+            .unwrap();
+            bottom -= letter_height;
+        }
+        writeln!(
+            svg,
+            r#"<text x="{x}" y="{y}" font-family="{FONT}" font-size="12" text-anchor="middle">{position}</text>"#,
+            x = x + COLUMN_WIDTH / 2.0,
+            y = baseline + 18.0,
+            position = i + 1
+        )
+        .unwrap();
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+pub(crate) fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod test {
+    use super::{information_content, render_logo};
+
+    #[test]
+    pub fn test_render_logo() {
+        let profile = vec![
+            vec![1.0, 0.5, 0.25],
+            vec![0.0, 0.0, 0.25],
+            vec![0.0, 0.5, 0.25],
+            vec![0.0, 0.0, 0.25],
+        ];
+        let information = information_content(&profile);
+        assert!((information[0] - 2.0).abs() < 1e-9);
+        assert!((information[1] - 1.0).abs() < 1e-9);
+        assert!(information[2].abs() < 1e-9);
+        let svg = render_logo("A<R>N", &profile);
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains("<title>A&lt;R&gt;N</title>"));
+        // one letter in the first column, two in the second and none in the third, which holds no information
+        assert_eq!(svg.matches("lengthAdjust").count(), 3);
+        assert!(svg.contains("scale(1 2.7933)"));
+    }
+}
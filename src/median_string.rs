@@ -1,11 +1,12 @@
 use std::collections::HashSet;
 
+use crate::progress::ProgressReporter;
+use crate::sequence::{candidate_kmers, reverse_complement, MotifInstance};
 use crate::Error;
-use indicatif::{ProgressBar, ProgressStyle};
 use rayon::prelude::*;
 use tracing::{trace, warn};
 #[tracing::instrument(skip_all)]
-fn hamming_distance(string1: &str, string2: &str) -> usize {
+pub(crate) fn hamming_distance(string1: &str, string2: &str) -> usize {
     trace!("Hamming distance between {} and {}", string1, string2);
     // scan linearly across both strings to find how many differences they have between each other
     let length = string1.chars().count();
@@ -43,7 +44,7 @@ fn neighbors(pattern: String, d: usize) -> HashSet<String> {
     for text in suffix_neighbors.iter() {
         if hamming_distance(&pattern[1..], text) < d {
             // this line is messy I apologize
-            for nuc in vec![
+            for nuc in [
                 "A".to_string(),
                 "C".to_string(),
                 "G".to_string(),
@@ -57,44 +58,49 @@ fn neighbors(pattern: String, d: usize) -> HashSet<String> {
     }
     neighborhood
 }
-#[tracing::instrument(skip(dna))]
-pub fn median_string(k: usize, dna: &[String]) -> Result<String, Error> {
+#[tracing::instrument(skip(dna, progress))]
+pub fn median_string(
+    k: usize,
+    dna: &[String],
+    both_strands: bool,
+    progress: &dyn ProgressReporter,
+) -> Result<String, Error> {
     trace!("Finding median string of length {} in {:?}", k, dna);
     let mut distance = usize::MAX;
     let dummy_string = "A".repeat(k);
     let patterns = neighbors(dummy_string, k);
     let mut median = String::from("");
     let len = patterns.len();
-    let pb = ProgressBar::new(len.try_into().map_err(|_| Error::GenericError)?);
-    pb.println(format!(
-        "Starting median string search for {k}-mers in {len} sequences",
-    ));
-    let sty = ProgressStyle::with_template(
-        "[{elapsed_precise}] {spinner:.green} {bar:40.cyan/blue} {pos:>7}/{len:7} {msg} ({eta})",
-    )
-    .unwrap();
-    pb.set_style(sty);
-    pb.reset_eta();
-    pb.set_message("Initializing");
-    for (_i, pattern) in patterns.iter().enumerate() {
-        pb.set_message(format!("Checking pattern: {pattern}"));
-        pb.inc(1);
-        let pattern_distance = distance_between_pattern_and_strings(pattern, dna)?;
+    progress.start(
+        &format!("Starting median string search for {k}-mers in {len} sequences"),
+        len,
+    );
+    progress.set_status("Initializing");
+    for pattern in patterns.iter() {
+        progress.set_status(&format!("Checking pattern: {pattern}"));
+        progress.advance(1);
+        let pattern_distance = distance_between_pattern_and_strings(pattern, dna, both_strands)?;
         if distance > pattern_distance {
             distance = pattern_distance;
             median = pattern.to_string();
         }
     }
-    pb.finish_with_message("Done!");
+    progress.finish("Done!");
 
     Ok(median)
 }
 
 #[tracing::instrument(skip(dna))]
-fn distance_between_pattern_and_strings(pattern: &str, dna: &[String]) -> Result<usize, Error> {
+fn distance_between_pattern_and_strings(
+    pattern: &str,
+    dna: &[String],
+    both_strands: bool,
+) -> Result<usize, Error> {
     let k = pattern.chars().count();
+    // matching the reverse complement of the pattern is the same as matching the pattern on the minus strand
+    let reverse_pattern = reverse_complement(pattern);
     let mut distance: usize = 0;
-    for (_i, seq) in dna.iter().enumerate() {
+    for seq in dna.iter() {
         let mut hammingdist = usize::MAX;
         let seq_len = seq.chars().count();
         if k > seq_len {
@@ -103,7 +109,10 @@ fn distance_between_pattern_and_strings(pattern: &str, dna: &[String]) -> Result
         }
         for i in 0..seq_len - k + 1 {
             let kmer = &seq[i..i + k].to_string();
-            let new_hamming = hamming_distance(pattern, kmer);
+            let mut new_hamming = hamming_distance(pattern, kmer);
+            if both_strands {
+                new_hamming = new_hamming.min(hamming_distance(&reverse_pattern, kmer));
+            }
             if hammingdist > new_hamming {
                 hammingdist = new_hamming;
             }
@@ -112,3 +121,55 @@ fn distance_between_pattern_and_strings(pattern: &str, dna: &[String]) -> Result
     }
     Ok(distance)
 }
+
+/// The kmer of every sequence closest to the pattern, read on the strand where it matches best
+#[tracing::instrument(skip(dna))]
+pub(crate) fn closest_sites(
+    pattern: &str,
+    dna: &[String],
+    both_strands: bool,
+) -> Vec<MotifInstance> {
+    dna.iter()
+        .enumerate()
+        .filter_map(|(index, sequence)| {
+            // the first of equally close kmers, so the forward strand wins a tie at the same offset
+            candidate_kmers(index, sequence, pattern.len(), both_strands)
+                .into_iter()
+                .min_by_key(|candidate| hamming_distance(pattern, &candidate.instance.kmer))
+                .map(|candidate| candidate.instance)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::closest_sites;
+    use crate::sequence::{SitePosition, Strand};
+
+    #[test]
+    pub fn test_closest_sites() {
+        // GTCAA is the reverse complement of TTGAC
+        let dna = vec!["ACGTTTGACGGA".to_string(), "CCGTCAAACC".to_string()];
+        let sites = closest_sites("TTGAC", &dna, true);
+        assert_eq!(sites.len(), 2);
+        assert_eq!(sites[0].kmer, "TTGAC");
+        assert_eq!(sites[0].strand, Strand::Forward);
+        assert_eq!(
+            sites[1].position,
+            Some(SitePosition {
+                sequence: 1,
+                offset: 2
+            })
+        );
+        assert_eq!(sites[1].kmer, "TTGAC");
+        assert_eq!(sites[1].strand, Strand::Reverse);
+    }
+
+    #[test]
+    pub fn test_closest_sites_forward_only() {
+        let dna = vec!["CCGTCAAACC".to_string()];
+        let sites = closest_sites("TTGAC", &dna, false);
+        assert_eq!(sites[0].strand, Strand::Forward);
+        assert_ne!(sites[0].kmer, "TTGAC");
+    }
+}
@@ -0,0 +1,109 @@
+use std::io::{self, Write};
+
+const NUCLEOTIDES: [char; 4] = ['A', 'C', 'G', 'T'];
+
+/// A motif as written to a MEME minimal format file
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemeMotif {
+    pub name: String,
+    /// ACGT probabilities, one row per nucleotide and one column per position
+    pub profile: Vec<Vec<f64>>,
+    /// number of sites the probabilities were estimated from
+    pub sites: usize,
+    pub e_value: Option<f64>,
+}
+
+/// Write the motifs in the MEME minimal format read by Tomtom, FIMO and the rest of the MEME suite
+pub fn write_meme<W: Write>(
+    writer: &mut W,
+    motifs: &[MemeMotif],
+    background: [f64; 4],
+    both_strands: bool,
+) -> io::Result<()> {
+    writeln!(writer, "MEME version 4")?;
+    writeln!(writer)?;
+    writeln!(writer, "ALPHABET= ACGT")?;
+    writeln!(writer)?;
+    writeln!(
+        writer,
+        "strands: {}",
+        if both_strands { "+ -" } else { "+" }
+    )?;
+    writeln!(writer)?;
+    writeln!(writer, "Background letter frequencies")?;
+    let frequencies: Vec<String> = NUCLEOTIDES
+        .iter()
+        .zip(background)
+        .map(|(nuc, frequency)| format!("{nuc} {frequency:.3}"))
+        .collect();
+    writeln!(writer, "{}", frequencies.join(" "))?;
+    for (i, motif) in motifs.iter().enumerate() {
+        let width = motif.profile.first().map_or(0, |row| row.len());
+        writeln!(writer)?;
+        // names can repeat, e.g. the same degenerate consensus for two motifs of a run, but identifiers must be unique
+        writeln!(writer, "MOTIF {}-{} {}", i + 1, motif.name, motif.name)?;
+        write!(
+            writer,
+            "letter-probability matrix: alength= 4 w= {} nsites= {}",
+            width, motif.sites
+        )?;
+        if let Some(e_value) = motif.e_value {
+            write!(writer, " E= {:.3e}", e_value)?;
+        }
+        writeln!(writer)?;
+        for position in 0..width {
+            let column: Vec<String> = motif
+                .profile
+                .iter()
+                .map(|row| format!("{:.6}", row[position]))
+                .collect();
+            writeln!(writer, " {}", column.join("\t"))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{write_meme, MemeMotif};
+
+    #[test]
+    pub fn test_write_meme() {
+        let motifs = vec![MemeMotif {
+            name: "TR".to_string(),
+            profile: vec![
+                vec![0.0, 0.5],
+                vec![0.0, 0.0],
+                vec![0.0, 0.5],
+                vec![1.0, 0.0],
+            ],
+            sites: 2,
+            e_value: Some(0.02),
+        }];
+        let mut meme = vec![];
+        write_meme(&mut meme, &motifs, [0.3, 0.2, 0.2, 0.3], true).unwrap();
+        let meme = String::from_utf8(meme).unwrap();
+        let expected = "MEME version 4\n\nALPHABET= ACGT\n\nstrands: + -\n\n\
+            Background letter frequencies\nA 0.300 C 0.200 G 0.200 T 0.300\n\n\
+            MOTIF 1-TR TR\n\
+            letter-probability matrix: alength= 4 w= 2 nsites= 2 E= 2.000e-2\n \
+            0.000000\t0.000000\t0.000000\t1.000000\n \
+            0.500000\t0.000000\t0.500000\t0.000000\n";
+        assert_eq!(meme, expected);
+    }
+
+    #[test]
+    pub fn test_write_meme_unique_identifiers() {
+        let motif = MemeMotif {
+            name: "TR".to_string(),
+            profile: vec![vec![1.0], vec![0.0], vec![0.0], vec![0.0]],
+            sites: 1,
+            e_value: None,
+        };
+        let mut meme = vec![];
+        write_meme(&mut meme, &[motif.clone(), motif], [0.25; 4], false).unwrap();
+        let meme = String::from_utf8(meme).unwrap();
+        assert!(meme.contains("MOTIF 1-TR TR\n"));
+        assert!(meme.contains("MOTIF 2-TR TR\n"));
+    }
+}
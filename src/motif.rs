@@ -0,0 +1,399 @@
+use std::fmt::Display;
+
+use crate::background::Background;
+use crate::iupac::{iupac_consensus, IupacThresholds};
+use crate::logo::information_content;
+use crate::median_string::hamming_distance;
+use crate::scoring::ScoringMethod;
+use crate::sequence::{reverse_complement, MotifInstance, Strand};
+use crate::{
+    consensus_string, generate_count_matrix, generate_probability,
+    generate_profile_given_motif_matrix, scoring_function, Error,
+};
+
+/// Letters that motifs are written in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Alphabet {
+    /// A, C, G and T, in the order of the rows of count matrices and profiles
+    #[default]
+    Dna,
+}
+
+impl Alphabet {
+    pub fn letters(&self) -> &'static [char] {
+        match self {
+            Alphabet::Dna => &['A', 'C', 'G', 'T'],
+        }
+    }
+
+    /// Number of letters, which is the number of rows of a count matrix or profile
+    pub fn size(&self) -> usize {
+        self.letters().len()
+    }
+
+    /// Row of the letter in count matrices and profiles
+    pub fn index(&self, letter: char) -> Option<usize> {
+        self.letters().iter().position(|&l| l == letter)
+    }
+}
+
+fn check_letters(kmer: &str, alphabet: Alphabet) -> Result<(), Error> {
+    if kmer.is_empty() {
+        return Err(Error::InvalidMotifLength);
+    }
+    if kmer.chars().any(|letter| alphabet.index(letter).is_none()) {
+        return Err(Error::InvalidNucleotideError);
+    }
+    Ok(())
+}
+
+/// A kmer written in the letters of its alphabet
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Motif {
+    kmer: String,
+    alphabet: Alphabet,
+}
+
+impl Motif {
+    /// The kmer in upper case, which fails if it is empty or holds letters outside the alphabet
+    pub fn new(kmer: &str, alphabet: Alphabet) -> Result<Motif, Error> {
+        let kmer = kmer.to_uppercase();
+        check_letters(&kmer, alphabet)?;
+        Ok(Motif { kmer, alphabet })
+    }
+
+    pub fn k(&self) -> usize {
+        self.kmer.len()
+    }
+
+    pub fn alphabet(&self) -> Alphabet {
+        self.alphabet
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.kmer
+    }
+
+    pub fn reverse_complement(&self) -> Motif {
+        Motif {
+            kmer: reverse_complement(&self.kmer),
+            alphabet: self.alphabet,
+        }
+    }
+
+    /// Number of positions the motifs differ at, or None when they aren't the same length
+    pub fn hamming_distance(&self, other: &Motif) -> Option<usize> {
+        (self.k() == other.k()).then(|| hamming_distance(&self.kmer, &other.kmer))
+    }
+}
+
+impl AsRef<str> for Motif {
+    fn as_ref(&self) -> &str {
+        &self.kmer
+    }
+}
+
+impl Display for Motif {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.kmer)
+    }
+}
+
+/// How often every letter occurs at every position of the sites of a motif
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CountMatrix {
+    /// one row per letter of the alphabet and one column per position
+    counts: Vec<Vec<usize>>,
+    alphabet: Alphabet,
+}
+
+impl CountMatrix {
+    pub fn k(&self) -> usize {
+        self.counts.first().map_or(0, |row| row.len())
+    }
+
+    pub fn alphabet(&self) -> Alphabet {
+        self.alphabet
+    }
+
+    /// One row per letter of the alphabet and one column per position
+    pub fn rows(&self) -> &[Vec<usize>] {
+        &self.counts
+    }
+
+    /// Occurrences of the letter at the 0-based position
+    pub fn count(&self, letter: char, position: usize) -> Option<usize> {
+        self.counts
+            .get(self.alphabet.index(letter)?)?
+            .get(position)
+            .copied()
+    }
+
+    /// Frequencies of the letters at every position
+    pub fn profile(&self) -> Profile {
+        let k = self.k();
+        let mut probabilities = vec![vec![0.0; k]; self.counts.len()];
+        for i in 0..k {
+            let sum: usize = self.counts.iter().map(|row| row[i]).sum();
+            for (row, counts) in probabilities.iter_mut().zip(&self.counts) {
+                // an empty column is spread evenly over the letters
+                row[i] = if sum > 0 {
+                    counts[i] as f64 / sum as f64
+                } else {
+                    1.0 / self.alphabet.size() as f64
+                };
+            }
+        }
+        Profile {
+            probabilities,
+            alphabet: self.alphabet,
+        }
+    }
+}
+
+/// Probability of every letter at every position of a motif, also known as a position probability matrix
+#[derive(Debug, Clone, PartialEq)]
+pub struct Profile {
+    /// one row per letter of the alphabet and one column per position
+    probabilities: Vec<Vec<f64>>,
+    alphabet: Alphabet,
+}
+
+impl Profile {
+    /// A profile from one row of probabilities per letter, where every column must add up to 1
+    pub fn new(rows: Vec<Vec<f64>>, alphabet: Alphabet) -> Result<Profile, Error> {
+        let k = rows.first().map_or(0, |row| row.len());
+        if rows.len() != alphabet.size() || k == 0 || rows.iter().any(|row| row.len() != k) {
+            return Err(Error::InvalidMotifLength);
+        }
+        for i in 0..k {
+            let sum: f64 = rows.iter().map(|row| row[i]).sum();
+            if rows.iter().any(|row| row[i] < 0.0) || (sum - 1.0).abs() > 1e-6 {
+                return Err(Error::InvalidInputError);
+            }
+        }
+        Ok(Profile {
+            probabilities: rows,
+            alphabet,
+        })
+    }
+
+    pub fn k(&self) -> usize {
+        self.probabilities.first().map_or(0, |row| row.len())
+    }
+
+    pub fn alphabet(&self) -> Alphabet {
+        self.alphabet
+    }
+
+    /// One row per letter of the alphabet and one column per position
+    pub fn rows(&self) -> &[Vec<f64>] {
+        &self.probabilities
+    }
+
+    /// Probability of the kmer being generated by the profile, for a kmer of length k
+    ///
+    /// Letters outside the alphabet, such as masked `N`s, don't change the probability.
+    pub fn probability(&self, kmer: &str) -> Result<f64, Error> {
+        if kmer.chars().count() != self.k() {
+            return Err(Error::InvalidKmerLength);
+        }
+        Ok(generate_probability(kmer, &self.probabilities))
+    }
+
+    /// The most likely letter at every position, the first in alphabet order on ties
+    pub fn consensus(&self) -> Motif {
+        let letters = self.alphabet.letters();
+        let kmer = (0..self.k())
+            .map(|i| {
+                let (best, _) = self
+                    .probabilities
+                    .iter()
+                    .map(|row| row[i])
+                    .enumerate()
+                    .fold((0, f64::MIN), |best, (j, probability)| {
+                        if probability > best.1 {
+                            (j, probability)
+                        } else {
+                            best
+                        }
+                    });
+                letters[best]
+            })
+            .collect();
+        Motif {
+            kmer,
+            alphabet: self.alphabet,
+        }
+    }
+
+    /// Information content of every position in bits, against an even background
+    pub fn information_content(&self) -> Vec<f64> {
+        information_content(&self.probabilities)
+    }
+}
+
+/// The sites of one motif, such as the one site per sequence returned by the searches
+#[derive(Debug, Clone, PartialEq)]
+pub struct MotifSet {
+    instances: Vec<MotifInstance>,
+    k: usize,
+    alphabet: Alphabet,
+}
+
+impl MotifSet {
+    /// A set of DNA sites, which must all be the same length
+    pub fn new(instances: Vec<MotifInstance>) -> Result<MotifSet, Error> {
+        let alphabet = Alphabet::Dna;
+        let k = instances.first().ok_or(Error::NoMotifsFound)?.kmer.len();
+        for instance in &instances {
+            if instance.kmer.len() != k {
+                return Err(Error::InvalidMotifLength);
+            }
+            check_letters(&instance.kmer, alphabet)?;
+        }
+        Ok(MotifSet {
+            instances,
+            k,
+            alphabet,
+        })
+    }
+
+    /// A set of sites on the forward strand without positions
+    pub fn from_kmers<S: AsRef<str>>(kmers: &[S]) -> Result<MotifSet, Error> {
+        MotifSet::new(
+            kmers
+                .iter()
+                .map(|kmer| MotifInstance::new(kmer.as_ref().to_uppercase(), Strand::Forward))
+                .collect(),
+        )
+    }
+
+    pub fn k(&self) -> usize {
+        self.k
+    }
+
+    pub fn alphabet(&self) -> Alphabet {
+        self.alphabet
+    }
+
+    pub fn instances(&self) -> &[MotifInstance] {
+        &self.instances
+    }
+
+    pub fn into_instances(self) -> Vec<MotifInstance> {
+        self.instances
+    }
+
+    pub fn len(&self) -> usize {
+        self.instances.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.instances.is_empty()
+    }
+
+    /// Counts of the letters of the sites, with a pseudocount of 1 added to every entry when `pseudocounts` is set
+    pub fn count_matrix(&self, pseudocounts: bool) -> CountMatrix {
+        CountMatrix {
+            counts: generate_count_matrix(&self.instances, self.k, pseudocounts),
+            alphabet: self.alphabet,
+        }
+    }
+
+    /// Frequencies of the letters of the sites, with pseudocounts so no letter is impossible when `pseudocounts` is set
+    pub fn profile(&self, pseudocounts: bool) -> Result<Profile, Error> {
+        Ok(Profile {
+            probabilities: generate_profile_given_motif_matrix(&self.instances, pseudocounts)?,
+            alphabet: self.alphabet,
+        })
+    }
+
+    /// The most common letter at every position, the first in alphabet order on ties
+    pub fn consensus(&self) -> Result<Motif, Error> {
+        Ok(Motif {
+            kmer: consensus_string(&self.instances, self.k)?,
+            alphabet: self.alphabet,
+        })
+    }
+
+    /// Consensus written with IUPAC codes where a position is split between letters
+    pub fn degenerate_consensus(&self, thresholds: &IupacThresholds) -> Result<String, Error> {
+        iupac_consensus(&self.instances, thresholds)
+    }
+
+    /// Number of letters that differ from the most common letter of their position, summed over the positions
+    pub fn hamming_score(&self) -> usize {
+        scoring_function(&self.instances)
+    }
+
+    /// Score of the sites with any of the scoring methods of the searches
+    pub fn score(&self, scoring: ScoringMethod, background: &Background) -> f64 {
+        scoring.score(&self.instances, background)
+    }
+
+    /// The distinct kmers of the sites in alphabetical order
+    pub fn unique_kmers(&self) -> Vec<String> {
+        let mut kmers: Vec<String> = self
+            .instances
+            .iter()
+            .map(|instance| instance.kmer.clone())
+            .collect();
+        kmers.sort();
+        kmers.dedup();
+        kmers
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Alphabet, Motif, MotifSet, Profile};
+    use crate::background::Background;
+    use crate::scoring::ScoringMethod;
+    use crate::Error;
+
+    #[test]
+    pub fn test_motif_set() {
+        let motif = Motif::new("ttgaca", Alphabet::Dna).unwrap();
+        assert_eq!(motif.as_str(), "TTGACA");
+        assert_eq!(motif.reverse_complement().as_str(), "TGTCAA");
+        let other = Motif::new("TTGACT", Alphabet::Dna).unwrap();
+        assert_eq!(motif.hamming_distance(&other), Some(1));
+        assert!(matches!(
+            Motif::new("TTNACA", Alphabet::Dna),
+            Err(Error::InvalidNucleotideError)
+        ));
+
+        let set = MotifSet::from_kmers(&["TTGACA", "TTGACT", "TAGACA", "TTGACA"]).unwrap();
+        assert_eq!(set.k(), 6);
+        assert_eq!(set.len(), 4);
+        assert_eq!(set.consensus().unwrap(), motif);
+        assert_eq!(set.hamming_score(), 2);
+        assert_eq!(
+            set.score(ScoringMethod::Hamming, &Background::uniform()),
+            2.0
+        );
+        assert_eq!(set.unique_kmers(), vec!["TAGACA", "TTGACA", "TTGACT"]);
+        assert!(matches!(
+            MotifSet::from_kmers(&["TTGACA", "TTGAC"]),
+            Err(Error::InvalidMotifLength)
+        ));
+
+        let counts = set.count_matrix(false);
+        assert_eq!(counts.count('T', 1), Some(3));
+        assert_eq!(counts.count('A', 1), Some(1));
+        assert_eq!(counts.count('N', 1), None);
+        let profile = counts.profile();
+        assert_eq!(profile.rows()[3][5], 0.25);
+        assert_eq!(profile.consensus(), motif);
+        assert!((profile.probability("TTGACA").unwrap() - 0.75 * 0.75).abs() < 1e-9);
+        assert_eq!(profile.probability("CTGACA").unwrap(), 0.0);
+        assert!(profile.probability("TTGAC").is_err());
+        // with pseudocounts no kmer is impossible
+        let smoothed = set.profile(true).unwrap();
+        assert!(smoothed.probability("CCCCCC").unwrap() > 0.0);
+        assert_eq!(smoothed.information_content().len(), 6);
+        assert!(Profile::new(vec![vec![0.5]; 4], Alphabet::Dna).is_err());
+        assert!(Profile::new(vec![vec![0.25]; 4], Alphabet::Dna).is_ok());
+    }
+}
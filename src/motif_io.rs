@@ -0,0 +1,670 @@
+use std::fmt::Display;
+use std::fs;
+use std::io::{self, Write};
+
+use clap::ValueEnum;
+use tracing::{error, info};
+
+use crate::iupac::iupac_counts;
+use crate::Error;
+use serde::{Deserialize, Serialize};
+
+const NUCLEOTIDES: [char; 4] = ['A', 'C', 'G', 'T'];
+
+/// Motif file formats shared with other tools
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MotifFormat {
+    /// `>id name` followed by one `A [ ... ]` row per nucleotide
+    #[value(name = "jaspar")]
+    Jaspar,
+    /// `ID` and `P0` records with one numbered row per position, ended by `//`
+    #[value(name = "transfac")]
+    Transfac,
+    /// `>consensus<TAB>name<TAB>threshold` followed by one row of ACGT probabilities per position
+    #[value(name = "homer")]
+    Homer,
+}
+
+impl Display for MotifFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MotifFormat::Jaspar => write!(f, "jaspar"),
+            MotifFormat::Transfac => write!(f, "transfac"),
+            MotifFormat::Homer => write!(f, "homer"),
+        }
+    }
+}
+
+/// What the values of a matrix are, as given by the format it was read from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MatrixValues {
+    /// numbers of sites with each nucleotide, as in JASPAR and TRANSFAC files and the sites of a run
+    Counts,
+    /// probabilities of each nucleotide, as in HOMER and MEME files
+    Frequencies,
+}
+
+/// A named motif as ACGT counts or frequencies, one row per nucleotide and one column per position like `generate_count_matrix`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MotifMatrix {
+    pub name: String,
+    pub rows: Vec<Vec<f64>>,
+    pub values: MatrixValues,
+}
+
+impl MotifMatrix {
+    /// Count the nucleotides of the sites, where degenerate codes count equally towards each nucleotide they stand for
+    pub fn from_sites<S: AsRef<str>>(name: &str, sites: &[S]) -> Result<MotifMatrix, Error> {
+        let k = sites
+            .first()
+            .ok_or(Error::NoMotifsFound)?
+            .as_ref()
+            .chars()
+            .count();
+        if sites.iter().any(|site| site.as_ref().chars().count() != k) {
+            return Err(Error::InvalidMotifLength);
+        }
+        Ok(MotifMatrix::from_columns(
+            name,
+            &iupac_counts(sites, k),
+            MatrixValues::Counts,
+        ))
+    }
+
+    pub fn from_columns(name: &str, columns: &[[f64; 4]], values: MatrixValues) -> MotifMatrix {
+        let rows = (0..4)
+            .map(|row| columns.iter().map(|column| column[row]).collect())
+            .collect();
+        MotifMatrix {
+            name: name.to_string(),
+            rows,
+            values,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.rows.first().map_or(0, |row| row.len())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// ACGT values of every position
+    pub fn columns(&self) -> Vec<[f64; 4]> {
+        (0..self.len())
+            .map(|i| [0, 1, 2, 3].map(|row| self.rows[row][i]))
+            .collect()
+    }
+
+    /// Rows of probabilities, with every column summing to 1 (or spread evenly if it is empty)
+    pub fn frequencies(&self) -> Vec<Vec<f64>> {
+        let mut frequencies = vec![vec![0.0; self.len()]; 4];
+        for (i, column) in self.columns().iter().enumerate() {
+            let total: f64 = column.iter().sum();
+            for (row, value) in frequencies.iter_mut().zip(column) {
+                row[i] = if total > 0.0 { value / total } else { 0.25 };
+            }
+        }
+        frequencies
+    }
+}
+
+fn nucleotide_index(nuc: char) -> Option<usize> {
+    NUCLEOTIDES
+        .iter()
+        .position(|&n| n == nuc.to_ascii_uppercase())
+}
+
+/// Read the matrices in a file
+///
+/// Without a format the file is read as the output of a previous run, or as whichever of JASPAR, TRANSFAC and HOMER it
+/// looks like. A text results file gives one matrix per motif built from its sites, and MEME and JSON output files give
+/// the matrices they hold. JASPAR files can also be a single unnamed matrix of four rows, with or without nucleotide
+/// labels and brackets.
+#[tracing::instrument]
+pub fn read_matrices(path: &str, format: Option<MotifFormat>) -> Result<Vec<MotifMatrix>, Error> {
+    info!("Reading matrices from '{}'...", path);
+    let contents =
+        fs::read_to_string(path).map_err(|_| Error::FileNotFoundError(path.to_string()))?;
+    let format = match format {
+        Some(format) => format,
+        None => {
+            let first_line = contents
+                .lines()
+                .map(str::trim)
+                .find(|line| !line.is_empty())
+                .unwrap_or_default();
+            if first_line.starts_with("MotifFinder") {
+                return read_results_matrices(&contents);
+            }
+            if first_line.starts_with("MEME version") {
+                return parse_meme(&contents);
+            }
+            if first_line.starts_with('{') {
+                return parse_json_results(&contents);
+            }
+            detect_format(&contents).ok_or_else(|| {
+                error!(
+                    "Can't tell the format of '{}': matrices are read from the text, MEME or JSON output of a run, \
+                    or from JASPAR, TRANSFAC or HOMER files (set with --matrix-format)",
+                    path
+                );
+                Error::InvalidInputError
+            })?
+        }
+    };
+    info!("Reading matrices as {}", format);
+    parse_matrices(&contents, format)
+}
+
+fn detect_format(contents: &str) -> Option<MotifFormat> {
+    let lines: Vec<&str> = contents.lines().map(str::trim).collect();
+    if lines
+        .iter()
+        .any(|line| line.starts_with("P0") || line.starts_with("PO"))
+    {
+        return Some(MotifFormat::Transfac);
+    }
+    // HOMER headers carry a log odds threshold in their third field, and the rows hold four values each
+    let homer_header = lines
+        .iter()
+        .find(|line| line.starts_with('>'))
+        .is_some_and(|header| {
+            header
+                .split('\t')
+                .nth(2)
+                .is_some_and(|threshold| threshold.trim().parse::<f64>().is_ok())
+        });
+    if homer_header {
+        return Some(MotifFormat::Homer);
+    }
+    // JASPAR files only hold headers and rows of numbers, each optionally labelled with its nucleotide
+    let jaspar_line = |line: &&str| {
+        if line.is_empty() || line.starts_with('#') || line.starts_with('>') {
+            return true;
+        }
+        let line = line.replace(['[', ']', ':', '|'], " ");
+        let mut tokens = line.split_whitespace().peekable();
+        if tokens.peek().is_some_and(|token| {
+            token.len() == 1 && nucleotide_index(token.chars().next().unwrap()).is_some()
+        }) {
+            tokens.next();
+        }
+        tokens.all(|token| token.parse::<f64>().is_ok())
+    };
+    let has_rows = lines
+        .iter()
+        .any(|line| !line.is_empty() && !line.starts_with('#') && !line.starts_with('>'));
+    if has_rows && lines.iter().all(jaspar_line) {
+        return Some(MotifFormat::Jaspar);
+    }
+    None
+}
+
+pub fn parse_matrices(contents: &str, format: MotifFormat) -> Result<Vec<MotifMatrix>, Error> {
+    let matrices = match format {
+        MotifFormat::Jaspar => parse_jaspar(contents)?,
+        MotifFormat::Transfac => parse_transfac(contents)?,
+        MotifFormat::Homer => parse_homer(contents)?,
+    };
+    if matrices.is_empty() {
+        return Err(Error::NoMotifsFound);
+    }
+    Ok(matrices)
+}
+
+fn read_results_matrices(contents: &str) -> Result<Vec<MotifMatrix>, Error> {
+    // every motif section lists its sites as `>motif N` records
+    let mut sections: Vec<(String, Vec<String>)> = vec![("motif 1".to_string(), vec![])];
+    let mut site_next = false;
+    for line in contents.lines() {
+        let line = line.trim();
+        if site_next {
+            sections.last_mut().unwrap().1.push(line.to_string());
+            site_next = false;
+        } else if line.starts_with('>') {
+            site_next = true;
+        } else if let Some(rest) = line.strip_prefix("Motif ") {
+            let index = rest.split_whitespace().next().unwrap_or_default();
+            sections.retain(|(_, sites)| !sites.is_empty());
+            sections.push((format!("motif {}", index), vec![]));
+        }
+    }
+    sections.retain(|(_, sites)| !sites.is_empty());
+    if sections.is_empty() {
+        return Err(Error::NoMotifsFound);
+    }
+    sections
+        .into_iter()
+        .map(|(name, sites)| MotifMatrix::from_sites(&name, &sites))
+        .collect()
+}
+
+fn parse_meme(contents: &str) -> Result<Vec<MotifMatrix>, Error> {
+    // every `MOTIF id name` line is followed by a letter-probability matrix with one row of ACGT probabilities per position
+    let mut matrices = vec![];
+    let mut name = None;
+    let mut lines = contents.lines().map(str::trim);
+    while let Some(line) = lines.next() {
+        if let Some(rest) = line.strip_prefix("MOTIF") {
+            name = Some(rest.split_whitespace().collect::<Vec<&str>>().join(" "));
+        } else if line.starts_with("letter-probability matrix") {
+            let name = name.take().ok_or(Error::InvalidInputError)?;
+            let mut fields = line.split_whitespace();
+            let width = fields
+                .find(|field| *field == "w=")
+                .and_then(|_| fields.next())
+                .and_then(|width| width.parse::<usize>().ok())
+                .ok_or(Error::InvalidInputError)?;
+            let columns = lines
+                .by_ref()
+                .filter(|line| !line.is_empty())
+                .take(width)
+                .map(|line| {
+                    let values = line
+                        .split_whitespace()
+                        .map(|token| token.parse::<f64>())
+                        .collect::<Result<Vec<f64>, _>>()
+                        .map_err(|_| Error::InvalidInputError)?;
+                    <[f64; 4]>::try_from(values).map_err(|_| Error::InvalidInputError)
+                })
+                .collect::<Result<Vec<[f64; 4]>, Error>>()?;
+            if columns.is_empty() || columns.len() != width {
+                return Err(Error::InvalidMotifLength);
+            }
+            matrices.push(MotifMatrix::from_columns(
+                &name,
+                &columns,
+                MatrixValues::Frequencies,
+            ));
+        }
+    }
+    if matrices.is_empty() {
+        return Err(Error::NoMotifsFound);
+    }
+    Ok(matrices)
+}
+
+#[derive(Deserialize)]
+struct JsonResultsFile {
+    motifs: Vec<JsonResultsMotif>,
+}
+
+#[derive(Deserialize)]
+struct JsonResultsMotif {
+    counts: Vec<Vec<f64>>,
+}
+
+fn parse_json_results(contents: &str) -> Result<Vec<MotifMatrix>, Error> {
+    // every motif of a JSON results file carries the counts of its sites
+    let results: JsonResultsFile = serde_json::from_str(contents).map_err(|_| {
+        error!("The JSON file isn't the output of a run");
+        Error::InvalidInputError
+    })?;
+    if results.motifs.is_empty() {
+        return Err(Error::NoMotifsFound);
+    }
+    results
+        .motifs
+        .into_iter()
+        .enumerate()
+        .map(|(i, motif)| {
+            let rows: [Vec<f64>; 4] = motif
+                .counts
+                .try_into()
+                .map_err(|_| Error::InvalidInputError)?;
+            rows_matrix(&format!("motif {}", i + 1), rows.map(Some))
+        })
+        .collect()
+}
+
+fn parse_jaspar(contents: &str) -> Result<Vec<MotifMatrix>, Error> {
+    let mut matrices = vec![];
+    let mut name = "matrix".to_string();
+    let mut rows: [Option<Vec<f64>>; 4] = Default::default();
+    let mut next_row = 0;
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(rest) = line.strip_prefix('>') {
+            if rows.iter().any(Option::is_some) {
+                matrices.push(rows_matrix(&name, rows)?);
+            }
+            name = rest.split_whitespace().collect::<Vec<&str>>().join(" ");
+            rows = Default::default();
+            next_row = 0;
+            continue;
+        }
+        let line = line.replace(['[', ']', ':', '|'], " ");
+        let mut tokens = line.split_whitespace().peekable();
+        let row = match tokens
+            .peek()
+            .and_then(|token| token.chars().next())
+            .and_then(nucleotide_index)
+        {
+            Some(row) => {
+                tokens.next();
+                row
+            }
+            None => next_row,
+        };
+        if row > 3 || rows[row].is_some() {
+            return Err(Error::InvalidInputError);
+        }
+        let values = tokens
+            .map(|token| token.parse::<f64>())
+            .collect::<Result<Vec<f64>, _>>()
+            .map_err(|_| Error::InvalidInputError)?;
+        rows[row] = Some(values);
+        next_row = row + 1;
+    }
+    if rows.iter().any(Option::is_some) {
+        matrices.push(rows_matrix(&name, rows)?);
+    }
+    Ok(matrices)
+}
+
+fn rows_matrix(name: &str, rows: [Option<Vec<f64>>; 4]) -> Result<MotifMatrix, Error> {
+    let [Some(a), Some(c), Some(g), Some(t)] = rows else {
+        return Err(Error::InvalidInputError);
+    };
+    if a.is_empty() || [&c, &g, &t].iter().any(|row| row.len() != a.len()) {
+        return Err(Error::InvalidMotifLength);
+    }
+    Ok(MotifMatrix {
+        name: name.to_string(),
+        rows: vec![a, c, g, t],
+        values: MatrixValues::Counts,
+    })
+}
+
+fn parse_transfac(contents: &str) -> Result<Vec<MotifMatrix>, Error> {
+    let mut matrices = vec![];
+    let mut name = None;
+    let mut accession = None;
+    // position of each of A, C, G and T among the columns of the P0 line
+    let mut order: Option<[usize; 4]> = None;
+    let mut columns: Vec<[f64; 4]> = vec![];
+    for line in contents.lines() {
+        let line = line.trim();
+        let (tag, rest) = line.split_at(line.len().min(2));
+        let rest = rest.trim();
+        match tag {
+            "//" => {
+                finish_transfac(&mut matrices, name.take().or(accession.take()), &columns);
+                accession = None;
+                columns.clear();
+                order = None;
+            }
+            "ID" => name = Some(rest.to_string()),
+            "AC" => accession = Some(rest.to_string()),
+            "P0" | "PO" => {
+                let mut indices = [0; 4];
+                let labels: Vec<usize> = rest
+                    .split_whitespace()
+                    .map(|label| label.chars().next().and_then(nucleotide_index))
+                    .collect::<Option<Vec<usize>>>()
+                    .ok_or(Error::InvalidInputError)?;
+                if labels.len() != 4 {
+                    return Err(Error::InvalidInputError);
+                }
+                for (column, nuc) in labels.into_iter().enumerate() {
+                    indices[nuc] = column;
+                }
+                order = Some(indices);
+            }
+            _ => {
+                let Some(order) = order else {
+                    continue;
+                };
+                if !tag.chars().all(|c| c.is_ascii_digit()) || tag.is_empty() {
+                    continue;
+                }
+                let values: Vec<f64> = rest
+                    .split_whitespace()
+                    .take(4)
+                    .map(|token| token.parse::<f64>())
+                    .collect::<Result<Vec<f64>, _>>()
+                    .map_err(|_| Error::InvalidInputError)?;
+                if values.len() != 4 {
+                    return Err(Error::InvalidInputError);
+                }
+                columns.push(order.map(|column| values[column]));
+            }
+        }
+    }
+    finish_transfac(&mut matrices, name.or(accession), &columns);
+    Ok(matrices)
+}
+
+fn finish_transfac(matrices: &mut Vec<MotifMatrix>, name: Option<String>, columns: &[[f64; 4]]) {
+    if columns.is_empty() {
+        return;
+    }
+    let name = name.unwrap_or_else(|| format!("matrix {}", matrices.len() + 1));
+    matrices.push(MotifMatrix::from_columns(
+        &name,
+        columns,
+        MatrixValues::Counts,
+    ));
+}
+
+fn parse_homer(contents: &str) -> Result<Vec<MotifMatrix>, Error> {
+    let mut matrices: Vec<(String, Vec<[f64; 4]>)> = vec![];
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(rest) = line.strip_prefix('>') {
+            let mut fields = rest.split('\t');
+            let consensus = fields.next().unwrap_or_default();
+            let name = fields
+                .next()
+                .filter(|name| !name.is_empty())
+                .unwrap_or(consensus);
+            matrices.push((name.to_string(), vec![]));
+            continue;
+        }
+        let (_, columns) = matrices.last_mut().ok_or(Error::InvalidInputError)?;
+        let values: Vec<f64> = line
+            .split_whitespace()
+            .map(|token| token.parse::<f64>())
+            .collect::<Result<Vec<f64>, _>>()
+            .map_err(|_| Error::InvalidInputError)?;
+        let column: [f64; 4] = values.try_into().map_err(|_| Error::InvalidInputError)?;
+        columns.push(column);
+    }
+    matrices
+        .into_iter()
+        .map(|(name, columns)| {
+            if columns.is_empty() {
+                return Err(Error::InvalidMotifLength);
+            }
+            Ok(MotifMatrix::from_columns(
+                &name,
+                &columns,
+                MatrixValues::Frequencies,
+            ))
+        })
+        .collect()
+}
+
+/// Write the matrices in the given format
+///
+/// JASPAR and TRANSFAC keep the values as they are. HOMER holds probabilities, and its detection threshold
+/// is set to 60% of the best log odds score of the motif against an even background, as a starting point to tune.
+pub fn write_matrices<W: Write>(
+    writer: &mut W,
+    matrices: &[MotifMatrix],
+    format: MotifFormat,
+) -> io::Result<()> {
+    match format {
+        MotifFormat::Jaspar => write_jaspar(writer, matrices),
+        MotifFormat::Transfac => write_transfac(writer, matrices),
+        MotifFormat::Homer => write_homer(writer, matrices),
+    }
+}
+
+fn write_jaspar<W: Write>(writer: &mut W, matrices: &[MotifMatrix]) -> io::Result<()> {
+    for matrix in matrices {
+        writeln!(writer, ">{}", matrix.name)?;
+        for (nuc, row) in NUCLEOTIDES.iter().zip(&matrix.rows) {
+            let values: Vec<String> = row.iter().map(|value| value.to_string()).collect();
+            writeln!(writer, "{}  [ {} ]", nuc, values.join(" "))?;
+        }
+    }
+    Ok(())
+}
+
+fn write_transfac<W: Write>(writer: &mut W, matrices: &[MotifMatrix]) -> io::Result<()> {
+    for (i, matrix) in matrices.iter().enumerate() {
+        writeln!(writer, "AC  MotifFinder-{}", i + 1)?;
+        writeln!(writer, "XX")?;
+        writeln!(writer, "ID  {}", matrix.name)?;
+        writeln!(writer, "XX")?;
+        writeln!(writer, "P0\tA\tC\tG\tT")?;
+        for (position, column) in matrix.columns().iter().enumerate() {
+            let values: Vec<String> = column.iter().map(|value| value.to_string()).collect();
+            writeln!(writer, "{:02}\t{}", position + 1, values.join("\t"))?;
+        }
+        writeln!(writer, "XX")?;
+        writeln!(writer, "//")?;
+    }
+    Ok(())
+}
+
+fn write_homer<W: Write>(writer: &mut W, matrices: &[MotifMatrix]) -> io::Result<()> {
+    for matrix in matrices {
+        let frequencies = MotifMatrix {
+            name: matrix.name.clone(),
+            rows: matrix.frequencies(),
+            values: MatrixValues::Frequencies,
+        };
+        let columns = frequencies.columns();
+        let consensus: String = columns
+            .iter()
+            .map(|column| {
+                let best = (0..4)
+                    .max_by(|&a, &b| column[a].total_cmp(&column[b]))
+                    .unwrap_or(0);
+                NUCLEOTIDES[best]
+            })
+            .collect();
+        let best_score: f64 = columns
+            .iter()
+            .map(|column| {
+                column
+                    .iter()
+                    .map(|frequency| (frequency / 0.25).ln())
+                    .fold(f64::NEG_INFINITY, f64::max)
+            })
+            .sum();
+        writeln!(
+            writer,
+            ">{}\t{}\t{:.6}",
+            consensus,
+            matrix.name,
+            0.6 * best_score
+        )?;
+        for column in columns {
+            let values: Vec<String> = column.iter().map(|value| format!("{value:.3}")).collect();
+            writeln!(writer, "{}", values.join("\t"))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        detect_format, parse_json_results, parse_matrices, parse_meme, write_matrices,
+        MatrixValues, MotifFormat, MotifMatrix,
+    };
+    use crate::meme::{write_meme, MemeMotif};
+
+    #[test]
+    pub fn test_motif_formats() {
+        let matrix = MotifMatrix::from_sites("TATA box", &["TATA", "TATT", "TAWA"]).unwrap();
+        assert_eq!(matrix.rows[0], vec![0.0, 3.0, 0.5, 2.0]);
+        assert_eq!(matrix.rows[3], vec![3.0, 0.0, 2.5, 1.0]);
+        for format in [MotifFormat::Jaspar, MotifFormat::Transfac] {
+            let mut written = vec![];
+            write_matrices(&mut written, &[matrix.clone(), matrix.clone()], format).unwrap();
+            let written = String::from_utf8(written).unwrap();
+            assert_eq!(detect_format(&written), Some(format));
+            let read = parse_matrices(&written, format).unwrap();
+            assert_eq!(read, vec![matrix.clone(), matrix.clone()]);
+        }
+        // HOMER keeps probabilities rounded to three decimals
+        let mut written = vec![];
+        write_matrices(
+            &mut written,
+            std::slice::from_ref(&matrix),
+            MotifFormat::Homer,
+        )
+        .unwrap();
+        let written = String::from_utf8(written).unwrap();
+        assert!(written.starts_with(">TATA\tTATA box\t"));
+        assert_eq!(detect_format(&written), Some(MotifFormat::Homer));
+        let read = parse_matrices(&written, MotifFormat::Homer).unwrap();
+        assert_eq!(read[0].name, "TATA box");
+        assert_eq!(read[0].values, MatrixValues::Frequencies);
+        assert_eq!(read[0].columns()[2], [0.167, 0.0, 0.0, 0.833]);
+        // TRANSFAC columns can come in any order and rows can carry a consensus letter
+        let transfac = "AC  M00001\nXX\nP0      T      G      C      A\n01      1      0      0      9      A\n02      9      0      1      0      T\nXX\n//\n";
+        let read = parse_matrices(transfac, MotifFormat::Transfac).unwrap();
+        assert_eq!(read[0].name, "M00001");
+        assert_eq!(
+            read[0].columns(),
+            vec![[9.0, 0.0, 0.0, 1.0], [0.0, 1.0, 0.0, 9.0]]
+        );
+    }
+
+    #[test]
+    pub fn test_read_run_output() {
+        let motif = MemeMotif {
+            name: "TW".to_string(),
+            profile: vec![
+                vec![0.0, 0.5],
+                vec![0.0, 0.0],
+                vec![0.0, 0.0],
+                vec![1.0, 0.5],
+            ],
+            sites: 2,
+            e_value: None,
+        };
+        let mut meme = vec![];
+        write_meme(&mut meme, &[motif.clone(), motif], [0.25; 4], false).unwrap();
+        let read = parse_meme(&String::from_utf8(meme).unwrap()).unwrap();
+        assert_eq!(read.len(), 2);
+        assert_eq!(read[1].name, "2-TW TW");
+        assert_eq!(read[1].values, MatrixValues::Frequencies);
+        assert_eq!(
+            read[0].columns(),
+            vec![[0.0, 0.0, 0.0, 1.0], [0.5, 0.0, 0.0, 0.5]]
+        );
+
+        let json = r#"{"version": "0.9.2", "motifs": [{"consensus": "TA", "counts": [[0, 2], [0, 0], [0, 0], [2, 0]]}]}"#;
+        let read = parse_json_results(json).unwrap();
+        assert_eq!(read[0].name, "motif 1");
+        assert_eq!(read[0].values, MatrixValues::Counts);
+        assert_eq!(
+            read[0].columns(),
+            vec![[0.0, 0.0, 0.0, 2.0], [2.0, 0.0, 0.0, 0.0]]
+        );
+        assert!(parse_json_results(r#"{"motifs": []}"#).is_err());
+
+        // anything else isn't taken for JASPAR
+        assert_eq!(detect_format("MOTIF 1-TW TW\n0.5 0.5 0 0\n"), None);
+        assert_eq!(
+            detect_format("A [ 1 2 ]\nC [ 0 0 ]\n"),
+            Some(MotifFormat::Jaspar)
+        );
+    }
+}
@@ -0,0 +1,130 @@
+use std::fmt::Debug;
+use std::sync::Mutex;
+
+use indicatif::{ProgressBar, ProgressStyle};
+
+/// Receives the progress of the searches and the alignment
+///
+/// Every method does nothing unless it is overridden. The runs of a search report from rayon worker threads,
+/// so reporters must be `Send + Sync`.
+pub trait ProgressReporter: Debug + Send + Sync {
+    /// A task of `total` steps has started
+    fn start(&self, _message: &str, _total: usize) {}
+
+    /// `steps` more steps of the task are done
+    fn advance(&self, _steps: usize) {}
+
+    /// What the task is working on at the moment, such as the pattern being checked
+    fn set_status(&self, _status: &str) {}
+
+    /// A line of results worth showing while the task runs, such as the best kmers so far
+    fn message(&self, _line: &str) {}
+
+    /// The task is done
+    fn finish(&self, _message: &str) {}
+}
+
+/// Reporter that ignores all progress, the default for library callers
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoProgress;
+
+impl ProgressReporter for NoProgress {}
+
+/// Progress bars on the terminal, used by the command line
+#[derive(Debug)]
+pub(crate) struct ProgressBars {
+    // the bar of the current task
+    bar: Mutex<ProgressBar>,
+}
+
+impl Default for ProgressBars {
+    fn default() -> ProgressBars {
+        ProgressBars {
+            bar: Mutex::new(ProgressBar::hidden()),
+        }
+    }
+}
+
+impl ProgressBars {
+    fn bar(&self) -> ProgressBar {
+        // a bar is cheap to clone and every clone draws the same bar
+        self.bar.lock().unwrap().clone()
+    }
+}
+
+impl ProgressReporter for ProgressBars {
+    fn start(&self, message: &str, total: usize) {
+        let bar = ProgressBar::new(total as u64);
+        let sty = ProgressStyle::with_template(
+            "[{elapsed_precise}] {spinner:.green} {bar:40.cyan/blue} {pos:>7}/{len:7} {msg} ({eta})",
+        )
+        .unwrap();
+        bar.set_style(sty);
+        bar.reset_eta();
+        bar.println(message);
+        *self.bar.lock().unwrap() = bar;
+    }
+
+    fn advance(&self, steps: usize) {
+        self.bar().inc(steps as u64);
+    }
+
+    fn set_status(&self, status: &str) {
+        self.bar().set_message(status.to_string());
+    }
+
+    fn message(&self, line: &str) {
+        self.bar().suspend(|| println!("{line}"));
+    }
+
+    fn finish(&self, message: &str) {
+        self.bar().finish_with_message(message.to_string());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use super::ProgressReporter;
+    use crate::{run_randomized_motif_search, SearchOptions};
+
+    #[derive(Debug, Default)]
+    struct CountingReporter {
+        total: AtomicUsize,
+        steps: AtomicUsize,
+        finished: AtomicUsize,
+    }
+
+    impl ProgressReporter for CountingReporter {
+        fn start(&self, _message: &str, total: usize) {
+            self.total.store(total, Ordering::SeqCst);
+        }
+
+        fn advance(&self, steps: usize) {
+            self.steps.fetch_add(steps, Ordering::SeqCst);
+        }
+
+        fn finish(&self, _message: &str) {
+            self.finished.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    pub fn test_progress_reporter() {
+        let sequences = vec![
+            "TTTTACGTACGTTTTT".to_string(),
+            "GGACGTACGTGGGGGG".to_string(),
+        ];
+        let reporter = Arc::new(CountingReporter::default());
+        let options = SearchOptions {
+            progress: Some(reporter.clone()),
+            ..Default::default()
+        };
+        run_randomized_motif_search(&sequences, 6, 7, &options).unwrap();
+        assert_eq!(reporter.total.load(Ordering::SeqCst), 7);
+        assert_eq!(reporter.steps.load(Ordering::SeqCst), 7);
+        assert_eq!(reporter.finished.load(Ordering::SeqCst), 1);
+    }
+}
@@ -1,97 +1,89 @@
+use crate::sequence::{Candidate, MotifInstance};
+use crate::site_model::{random_initial_sites, sequence_candidates, Run};
 use crate::Error;
-use crate::{generate_probability, generate_profile_given_motif_matrix, scoring_function};
-use indicatif::{ParallelProgressIterator, ProgressBar, ProgressStyle};
-use rand::{thread_rng, Rng};
+use crate::{generate_profile_given_motif_matrix, run_rng, SearchOptions, SearchResult};
+use rand::Rng;
 use rayon::prelude::*;
 use tracing::trace;
-#[tracing::instrument(skip(dna))]
-fn randomized_motif_search(dna: &[String], k: usize) -> Result<Vec<String>, Error> {
-    let mut best_motifs = vec![];
-    for seq in dna {
-        let dna_length = seq.chars().count();
-        let start_index = thread_rng().gen_range(0..(dna_length - k + 1));
-        if k > dna_length {
-            continue;
-        }
-        best_motifs.push(seq[start_index..start_index + k].to_string());
-    }
 
-    let mut best_score = scoring_function(&best_motifs);
+#[tracing::instrument(skip(dna, options, rng))]
+fn randomized_motif_search<R: Rng>(
+    dna: &[String],
+    k: usize,
+    options: &SearchOptions,
+    rng: &mut R,
+) -> Result<Run, Error> {
+    let candidates = sequence_candidates(dna, k, options.both_strands)?;
+    let mut best_motifs = random_initial_sites(&candidates, rng);
+    let mut best_score = options.site_model.score(
+        &best_motifs,
+        &candidates,
+        options.scoring,
+        &options.background,
+    )?;
+    let mut score_trace = vec![best_score];
     loop {
-        let profile = generate_profile_given_motif_matrix(&best_motifs, true)?;
-        let motifs = generate_motifs_from_profile(&profile, dna, k);
-        let test_score = scoring_function(&motifs);
+        let sites: Vec<&MotifInstance> = best_motifs.iter().flatten().collect();
+        let profile = generate_profile_given_motif_matrix(&sites, true)?;
+        let motifs = generate_motifs_from_profile(&profile, &candidates, options);
+        let test_score =
+            options
+                .site_model
+                .score(&motifs, &candidates, options.scoring, &options.background)?;
         if test_score < best_score {
             best_score = test_score;
             best_motifs = motifs;
+            score_trace.push(best_score);
         } else {
-            return Ok(best_motifs);
-        }
-    }
-}
-#[tracing::instrument(skip(profile))]
-fn profile_most_probable_kmer(text: &str, k: usize, profile: &[Vec<f64>]) -> String {
-    // given a profile, and a DNA string, check all kmers to see which one is the most probable
-    let text_len = text.chars().count();
-    let mut best_probability_so_far = -1.0;
-    let dummy = "";
-    let mut best_kmer = dummy;
-
-    for i in 0..(text_len - k + 1) {
-        if k > text_len {
-            continue;
-        }
-        let kmer = &text[i..i + k];
-        let kmer_prob = generate_probability(kmer, profile);
-        if kmer_prob > best_probability_so_far {
-            best_kmer = kmer;
-            best_probability_so_far = kmer_prob;
+            return Ok((best_score, best_motifs, score_trace));
         }
     }
-
-    best_kmer.to_owned()
 }
 
-#[tracing::instrument(skip(profile, dna))]
-fn generate_motifs_from_profile(profile: &[Vec<f64>], dna: &[String], k: usize) -> Vec<String> {
-    let mut motifs: Vec<String> = vec![];
-    for seq in dna {
-        motifs.push(profile_most_probable_kmer(seq, k, profile));
+#[tracing::instrument(skip_all)]
+fn generate_motifs_from_profile(
+    profile: &[Vec<f64>],
+    candidates: &[Vec<Candidate>],
+    options: &SearchOptions,
+) -> Vec<Vec<MotifInstance>> {
+    let mut motifs: Vec<Vec<MotifInstance>> = vec![];
+    for kmers in candidates {
+        motifs.push(
+            options
+                .site_model
+                .most_probable_sites(kmers, profile, &options.background),
+        );
     }
     motifs
 }
-#[tracing::instrument(skip(dna))]
+#[tracing::instrument(skip(dna, options))]
 pub fn iterate_randomized_motif_search(
     dna: &[String],
     k: usize,
     runs: usize,
-) -> Result<Vec<String>, Error> {
-    let pb = ProgressBar::new(runs.try_into().map_err(|_| Error::InvalidNumberOfRuns)?);
+    options: &SearchOptions,
+) -> Result<SearchResult, Error> {
     trace!("Started randomized motif search");
-    pb.println(format!(
-        "Starting randomized motif search with {} runs",
-        runs
-    ));
-    let sty = ProgressStyle::with_template(
-        "[{elapsed_precise}] {spinner:.green} {bar:40.cyan/blue} {pos:>7}/{len:7} {msg} ({eta})",
-    )
-    .unwrap();
-    pb.set_style(sty);
-    pb.reset_eta();
+    let progress = options.progress();
+    progress.start(
+        &format!("Starting randomized motif search with {} runs", runs),
+        runs,
+    );
 
-    let mut result: Vec<(usize, Vec<String>)> = (1..=runs)
+    let mut result: Vec<Run> = (1..=runs)
         .into_par_iter()
-        .progress_with(pb.clone())
-        .map(|_i| {
-            let motifs = randomized_motif_search(dna, k)?;
-            let best_score = scoring_function(&motifs);
-            Ok((best_score, motifs))
+        .map(|i| {
+            let run = randomized_motif_search(dna, k, options, &mut run_rng(options.seed, i));
+            progress.advance(1);
+            run
         })
-        .collect::<Result<Vec<(usize, Vec<String>)>, Error>>()?;
-    result.par_sort_by(|a, b| a.0.cmp(&b.0));
+        .collect::<Result<Vec<Run>, Error>>()?;
+    result.par_sort_by(|a, b| a.0.total_cmp(&b.0));
     // dbg!(&result);
-    let motifs = result[0].1.clone();
-    let best_score = result[0].0;
-    pb.finish_with_message(format!("Done! Best score: {best_score}"));
-    Ok(motifs)
+    let (best_score, best_motifs, score_trace) = result.swap_remove(0);
+    progress.finish(&format!("Done! Best score: {best_score:.3}"));
+    Ok(SearchResult {
+        motifs: best_motifs.concat(),
+        score_trace,
+    })
 }
@@ -0,0 +1,264 @@
+use rayon::prelude::*;
+
+use crate::motif_io::MatrixValues;
+use crate::sequence::{reverse_complement, SequenceRecord, Strand};
+use crate::Error;
+use serde::Serialize;
+
+// column scores are rounded to this fraction of a bit when computing p-values
+const SCORE_RESOLUTION: f64 = 100.0;
+
+/// Position weight matrix of log-odds scores against a zero order background
+#[derive(Debug, Clone, PartialEq)]
+pub struct PositionWeightMatrix {
+    pub name: String,
+    /// log2 ratio of the motif and background probability of A, C, G and T in every column
+    pub log_odds: Vec<[f64; 4]>,
+    // probability under the background of scoring at least min_total + i rounded score units
+    tail: Vec<f64>,
+    min_total: i64,
+}
+
+impl PositionWeightMatrix {
+    /// Build the matrix from the ACGT counts or frequencies of every column
+    ///
+    /// Counts get a pseudocount of 1 spread according to the background and frequencies get 1% of the background mixed in,
+    /// so no nucleotide is impossible.
+    pub fn new(
+        name: &str,
+        columns: &[[f64; 4]],
+        values: MatrixValues,
+        background: [f64; 4],
+    ) -> Result<PositionWeightMatrix, Error> {
+        if columns.is_empty() {
+            return Err(Error::InvalidMotifLength);
+        }
+        let mut log_odds = Vec::with_capacity(columns.len());
+        for column in columns {
+            let sum: f64 = column.iter().sum();
+            if sum <= 0.0 || column.iter().any(|&count| count < 0.0) {
+                return Err(Error::InvalidInputError);
+            }
+            let mut scores = [0.0; 4];
+            for (j, score) in scores.iter_mut().enumerate() {
+                let probability = match values {
+                    MatrixValues::Counts => (column[j] + background[j]) / (sum + 1.0),
+                    MatrixValues::Frequencies => (column[j] / sum + 0.01 * background[j]) / 1.01,
+                };
+                *score = (probability / background[j]).log2();
+            }
+            log_odds.push(scores);
+        }
+        let (tail, min_total) = score_distribution(&log_odds, background);
+        Ok(PositionWeightMatrix {
+            name: name.to_string(),
+            log_odds,
+            tail,
+            min_total,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.log_odds.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.log_odds.is_empty()
+    }
+
+    /// Log-odds score of a kmer in bits, or None if it contains anything other than A, C, G or T
+    pub fn score(&self, kmer: &str) -> Option<f64> {
+        let mut score = 0.0;
+        for (column, nuc) in self.log_odds.iter().zip(kmer.chars()) {
+            score += column[nucleotide_index(nuc)?];
+        }
+        Some(score)
+    }
+
+    /// Probability that a random kmer drawn from the background scores at least as well as this one, with every
+    /// column score rounded to 0.01 bits
+    pub fn p_value(&self, kmer: &str) -> Option<f64> {
+        let mut total = 0;
+        for (column, nuc) in self.log_odds.iter().zip(kmer.chars()) {
+            total += rounded(column[nucleotide_index(nuc)?]);
+        }
+        let index = (total - self.min_total).clamp(0, self.tail.len() as i64 - 1);
+        Some(self.tail[index as usize])
+    }
+}
+
+fn nucleotide_index(nuc: char) -> Option<usize> {
+    match nuc {
+        'A' => Some(0),
+        'C' => Some(1),
+        'G' => Some(2),
+        'T' => Some(3),
+        _ => None,
+    }
+}
+
+fn rounded(score: f64) -> i64 {
+    (score * SCORE_RESOLUTION).round() as i64
+}
+
+fn score_distribution(log_odds: &[[f64; 4]], background: [f64; 4]) -> (Vec<f64>, i64) {
+    // exact distribution of the rounded score of a background kmer, built up one column at a time
+    let columns: Vec<[i64; 4]> = log_odds.iter().map(|column| column.map(rounded)).collect();
+    let min_total: i64 = columns
+        .iter()
+        .map(|column| column.iter().min().unwrap())
+        .sum();
+    let max_total: i64 = columns
+        .iter()
+        .map(|column| column.iter().max().unwrap())
+        .sum();
+    let mut distribution = vec![0.0; (max_total - min_total + 1) as usize];
+    distribution[0] = 1.0;
+    for column in &columns {
+        let column_min = column.iter().min().unwrap();
+        let mut next = vec![0.0; distribution.len()];
+        for (total, &p) in distribution.iter().enumerate().filter(|(_, &p)| p > 0.0) {
+            for (score, q) in column.iter().zip(background) {
+                next[total + (score - column_min) as usize] += p * q;
+            }
+        }
+        distribution = next;
+    }
+    // turn the distribution into P(score >= total)
+    let mut tail = distribution;
+    for i in (0..tail.len() - 1).rev() {
+        tail[i] += tail[i + 1];
+    }
+    (tail, min_total)
+}
+
+/// Which sites a scan reports
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScanThreshold {
+    /// log-odds score of at least this many bits
+    Score(f64),
+    /// p-value of at most this
+    PValue(f64),
+}
+
+/// A site scoring above the threshold in a scan
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ScanHit {
+    /// name of the matrix that matched
+    pub motif: String,
+    /// index of the sequence in the input
+    pub sequence: usize,
+    /// identifier of the sequence from its FASTA header
+    pub id: String,
+    /// start and end (exclusive) of the site on the forward strand
+    pub start: usize,
+    pub end: usize,
+    pub strand: Strand,
+    pub score: f64,
+    pub p_value: f64,
+    /// the site read 5' to 3' on its strand
+    pub site: String,
+}
+
+/// Report every site of the sequences that passes the threshold, in order of sequence and position
+#[tracing::instrument(skip(sequences, matrix))]
+pub fn scan_sequences(
+    sequences: &[SequenceRecord],
+    matrix: &PositionWeightMatrix,
+    threshold: ScanThreshold,
+    both_strands: bool,
+) -> Vec<ScanHit> {
+    let k = matrix.len();
+    sequences
+        .par_iter()
+        .enumerate()
+        .flat_map_iter(|(i, record)| {
+            let seq = &record.sequence;
+            let mut hits = vec![];
+            if seq.len() < k {
+                return hits;
+            }
+            for start in 0..=seq.len() - k {
+                let kmer = &seq[start..start + k];
+                let mut strands = vec![(kmer.to_string(), Strand::Forward)];
+                if both_strands {
+                    strands.push((reverse_complement(kmer), Strand::Reverse));
+                }
+                for (site, strand) in strands {
+                    let (Some(score), Some(p_value)) = (matrix.score(&site), matrix.p_value(&site))
+                    else {
+                        continue;
+                    };
+                    let passes = match threshold {
+                        ScanThreshold::Score(min_score) => score >= min_score,
+                        ScanThreshold::PValue(max_p_value) => p_value <= max_p_value,
+                    };
+                    if passes {
+                        hits.push(ScanHit {
+                            motif: matrix.name.clone(),
+                            sequence: i,
+                            id: record.id.clone(),
+                            start,
+                            end: start + k,
+                            strand,
+                            score,
+                            p_value,
+                            site,
+                        });
+                    }
+                }
+            }
+            hits
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{scan_sequences, PositionWeightMatrix, ScanThreshold};
+    use crate::motif_io::{parse_matrices, MatrixValues, MotifFormat};
+    use crate::{SequenceRecord, Strand};
+
+    #[test]
+    pub fn test_scan_sequences() {
+        let matrices = parse_matrices(
+            ">TATA\nA [ 0 10 0 10 ]\nC [ 0 0 0 0 ]\nG [ 0 0 0 0 ]\nT [ 10 0 10 0 ]\n",
+            MotifFormat::Jaspar,
+        )
+        .unwrap();
+        assert_eq!(matrices[0].name, "TATA");
+        let matrix = PositionWeightMatrix::new(
+            &matrices[0].name,
+            &matrices[0].columns(),
+            matrices[0].values,
+            [0.25; 4],
+        )
+        .unwrap();
+        // the best site has a p-value of 1 in 4^4
+        let best = matrix.p_value("TATA").unwrap();
+        assert!((best - 1.0 / 256.0).abs() < 1e-9);
+        assert!(matrix.score("TATA").unwrap() > matrix.score("TATT").unwrap());
+        assert!((matrix.p_value("GGGG").unwrap() - 1.0).abs() < 1e-9);
+        // a matrix of one site still holds counts, whose columns sum to 1 like frequencies
+        let one_site = [[0.0, 0.0, 0.0, 1.0]];
+        let counts =
+            PositionWeightMatrix::new("T", &one_site, MatrixValues::Counts, [0.25; 4]).unwrap();
+        assert!((counts.score("T").unwrap() - (1.25f64 / 2.0 / 0.25).log2()).abs() < 1e-9);
+        let frequencies =
+            PositionWeightMatrix::new("T", &one_site, MatrixValues::Frequencies, [0.25; 4])
+                .unwrap();
+        assert!(frequencies.score("T").unwrap() > counts.score("T").unwrap());
+
+        let sequences = SequenceRecord::from_sequences(&["GGTATAGG", "CCTATACC"]);
+        let hits = scan_sequences(&sequences, &matrix, ScanThreshold::PValue(0.005), false);
+        assert_eq!(hits.len(), 2);
+        assert_eq!((hits[1].sequence, hits[1].start, hits[1].end), (1, 2, 6));
+        assert_eq!(hits[1].id, "seq1");
+        // TATA is its own reverse complement so it is found on both strands
+        let hits = scan_sequences(&sequences, &matrix, ScanThreshold::PValue(0.005), true);
+        assert_eq!(hits.len(), 4);
+        assert_eq!(hits[1].strand, Strand::Reverse);
+        let hits = scan_sequences(&sequences, &matrix, ScanThreshold::Score(100.0), true);
+        assert!(hits.is_empty());
+    }
+}
@@ -0,0 +1,120 @@
+use std::fmt::Display;
+
+use clap::ValueEnum;
+
+use crate::background::Background;
+use crate::{generate_count_matrix, scoring_function};
+use serde::Serialize;
+
+/// How a set of motifs is scored
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ScoringMethod {
+    /// number of nucleotides that differ from the most common nucleotide of their column, lower is better
+    #[default]
+    #[value(name = "hamming")]
+    Hamming,
+    /// total Shannon entropy of the columns in bits, lower is better
+    #[value(name = "entropy")]
+    Entropy,
+    /// total relative entropy (information content) of the columns against the background in bits, higher is better
+    #[value(name = "relative-entropy")]
+    RelativeEntropy,
+}
+
+impl Display for ScoringMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScoringMethod::Hamming => write!(f, "hamming"),
+            ScoringMethod::Entropy => write!(f, "entropy"),
+            ScoringMethod::RelativeEntropy => write!(f, "relative-entropy"),
+        }
+    }
+}
+
+impl ScoringMethod {
+    /// Score of a set of motifs in the units of the method
+    #[tracing::instrument(skip_all)]
+    pub fn score<S: AsRef<str>>(&self, motifs: &[S], background: &Background) -> f64 {
+        if motifs.is_empty() {
+            return 0.0;
+        }
+        match self {
+            ScoringMethod::Hamming => scoring_function(motifs) as f64,
+            ScoringMethod::Entropy => column_frequencies(motifs)
+                .iter()
+                .map(|column| {
+                    column
+                        .iter()
+                        .filter(|&&p| p > 0.0)
+                        .map(|p| -p * p.log2())
+                        .sum::<f64>()
+                })
+                .sum(),
+            ScoringMethod::RelativeEntropy => {
+                let frequencies = background.frequencies();
+                column_frequencies(motifs)
+                    .iter()
+                    .map(|column| {
+                        column
+                            .iter()
+                            .zip(frequencies)
+                            .filter(|(&p, _)| p > 0.0)
+                            .map(|(p, q)| p * (p / q).log2())
+                            .sum::<f64>()
+                    })
+                    .sum()
+            }
+        }
+    }
+
+    pub fn higher_is_better(&self) -> bool {
+        matches!(self, ScoringMethod::RelativeEntropy)
+    }
+
+    /// Score turned around where needed so that lower is always better
+    pub(crate) fn cost<S: AsRef<str>>(&self, motifs: &[S], background: &Background) -> f64 {
+        let score = self.score(motifs, background);
+        if self.higher_is_better() {
+            -score
+        } else {
+            score
+        }
+    }
+}
+
+fn column_frequencies<S: AsRef<str>>(motifs: &[S]) -> Vec<[f64; 4]> {
+    // observed nucleotide frequencies of every column, without pseudocounts
+    let k = motifs[0].as_ref().len();
+    let count_matrix = generate_count_matrix(motifs, k, false);
+    (0..k)
+        .map(|i| {
+            let sum: usize = count_matrix.iter().map(|row| row[i]).sum();
+            let mut column = [0.0; 4];
+            if sum > 0 {
+                for (j, row) in count_matrix.iter().enumerate() {
+                    column[j] = row[i] as f64 / sum as f64;
+                }
+            }
+            column
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::ScoringMethod;
+    use crate::background::Background;
+
+    #[test]
+    pub fn test_scores() {
+        let background = Background::uniform();
+        // the first column is split between two nucleotides, the second between all four
+        let motifs = ["AA", "AC", "GG", "GT"];
+        assert_eq!(ScoringMethod::Hamming.score(&motifs, &background), 5.0);
+        let entropy = ScoringMethod::Entropy.score(&motifs, &background);
+        assert!((entropy - 3.0).abs() < 1e-9);
+        let relative_entropy = ScoringMethod::RelativeEntropy.score(&motifs, &background);
+        assert!((relative_entropy - 1.0).abs() < 1e-9);
+    }
+}
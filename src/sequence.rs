@@ -0,0 +1,198 @@
+use std::fmt::Display;
+
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub enum Strand {
+    #[serde(rename = "+")]
+    Forward,
+    #[serde(rename = "-")]
+    Reverse,
+}
+
+impl Display for Strand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Strand::Forward => write!(f, "+"),
+            Strand::Reverse => write!(f, "-"),
+        }
+    }
+}
+
+/// Where a motif instance lies in the input
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub struct SitePosition {
+    /// index of the sequence in the input
+    pub sequence: usize,
+    /// 0-based offset of the first nucleotide of the site in the forward strand of the sequence
+    pub offset: usize,
+}
+
+/// A k-mer chosen as a motif occurrence, read 5' to 3' on the strand it was found on
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub struct MotifInstance {
+    pub kmer: String,
+    pub strand: Strand,
+    /// None for kmers that weren't taken from a sequence
+    pub position: Option<SitePosition>,
+}
+
+impl MotifInstance {
+    pub fn new(kmer: String, strand: Strand) -> MotifInstance {
+        MotifInstance {
+            kmer,
+            strand,
+            position: None,
+        }
+    }
+}
+
+impl AsRef<str> for MotifInstance {
+    fn as_ref(&self) -> &str {
+        &self.kmer
+    }
+}
+
+/// A sequence of the input with the identifier and description from its FASTA header
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SequenceRecord {
+    pub id: String,
+    pub description: Option<String>,
+    pub sequence: String,
+}
+
+impl AsRef<str> for SequenceRecord {
+    fn as_ref(&self) -> &str {
+        &self.sequence
+    }
+}
+
+#[cfg(test)]
+impl SequenceRecord {
+    /// Records of the sequences named seq0, seq1 and so on, without descriptions
+    pub(crate) fn from_sequences(sequences: &[&str]) -> Vec<SequenceRecord> {
+        sequences
+            .iter()
+            .enumerate()
+            .map(|(i, sequence)| SequenceRecord {
+                id: format!("seq{i}"),
+                description: None,
+                sequence: sequence.to_string(),
+            })
+            .collect()
+    }
+}
+
+pub(crate) fn sequences_of(records: &[SequenceRecord]) -> Vec<String> {
+    records
+        .iter()
+        .map(|record| record.sequence.clone())
+        .collect()
+}
+
+pub fn reverse_complement(sequence: &str) -> String {
+    sequence
+        .chars()
+        .rev()
+        .map(|nuc| match nuc {
+            'A' => 'T',
+            'C' => 'G',
+            'G' => 'C',
+            'T' => 'A',
+            other => other,
+        })
+        .collect()
+}
+
+/// A kmer of a sequence that could be a motif site
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Candidate {
+    /// offset of the first nucleotide of the kmer in the forward strand of the sequence
+    pub offset: usize,
+    pub instance: MotifInstance,
+}
+
+impl AsRef<str> for Candidate {
+    fn as_ref(&self) -> &str {
+        &self.instance.kmer
+    }
+}
+
+#[tracing::instrument(skip(sequence))]
+pub(crate) fn candidate_kmers(
+    index: usize,
+    sequence: &str,
+    k: usize,
+    both_strands: bool,
+) -> Vec<Candidate> {
+    // every kmer of the sequence, plus the reverse complement of every kmer if both strands are searched
+    let sequence_len = sequence.chars().count();
+    let mut kmers = vec![];
+    if k > sequence_len {
+        return kmers;
+    }
+    for i in 0..sequence_len - k + 1 {
+        let kmer = &sequence[i..i + k];
+        if !kmer.chars().all(|nuc| matches!(nuc, 'A' | 'C' | 'G' | 'T')) {
+            // masked or ambiguous nucleotides can't be part of a site
+            continue;
+        }
+        let position = Some(SitePosition {
+            sequence: index,
+            offset: i,
+        });
+        kmers.push(Candidate {
+            offset: i,
+            instance: MotifInstance {
+                kmer: kmer.to_string(),
+                strand: Strand::Forward,
+                position,
+            },
+        });
+        if both_strands {
+            kmers.push(Candidate {
+                offset: i,
+                instance: MotifInstance {
+                    kmer: reverse_complement(kmer),
+                    strand: Strand::Reverse,
+                    position,
+                },
+            });
+        }
+    }
+    kmers
+}
+
+#[cfg(test)]
+mod test {
+    use super::{candidate_kmers, reverse_complement, SitePosition, Strand};
+
+    #[test]
+    pub fn test_reverse_complement() {
+        assert_eq!(reverse_complement("AACGTT"), "AACGTT");
+        assert_eq!(reverse_complement("ATGCC"), "GGCAT");
+    }
+
+    #[test]
+    pub fn test_candidate_kmers() {
+        let kmers = candidate_kmers(0, "AACG", 3, false);
+        assert_eq!(kmers.len(), 2);
+        let kmers = candidate_kmers(0, "AACG", 3, true);
+        assert_eq!(kmers.len(), 4);
+        assert_eq!(kmers[1].instance.kmer, "GTT");
+        assert_eq!(kmers[1].instance.strand, Strand::Reverse);
+        assert_eq!(kmers[3].offset, 1);
+        assert!(candidate_kmers(0, "AACG", 5, true).is_empty());
+        // kmers overlapping masked nucleotides are skipped but keep their offsets
+        let kmers = candidate_kmers(4, "ANACGT", 3, false);
+        assert_eq!(kmers.len(), 2);
+        assert_eq!(kmers[0].offset, 2);
+        assert_eq!(
+            kmers[1].instance.position,
+            Some(SitePosition {
+                sequence: 4,
+                offset: 3
+            })
+        );
+    }
+}
@@ -0,0 +1,152 @@
+use std::collections::BTreeMap;
+
+use rand::prelude::*;
+use serde::Serialize;
+
+/// Shuffle a sequence while keeping the count of every dinucleotide, and the first and last nucleotide, the same
+///
+/// Uses the Altschul-Erickson method: the sequence is a walk through the graph of its dinucleotides,
+/// and a random Eulerian walk through that graph that ends on the same nucleotide is another
+/// sequence with the same dinucleotides.
+pub fn dinucleotide_shuffle<R: Rng + ?Sized>(sequence: &str, rng: &mut R) -> String {
+    let nucs: Vec<char> = sequence.chars().collect();
+    if nucs.len() <= 2 {
+        return sequence.to_string();
+    }
+    let last = nucs[nucs.len() - 1];
+    let mut edges: BTreeMap<char, Vec<char>> = BTreeMap::new();
+    for pair in nucs.windows(2) {
+        edges.entry(pair[0]).or_default().push(pair[1]);
+    }
+    // pick the last edge out of every nucleotide so those edges form a tree leading to the last nucleotide,
+    // which guarantees the walk can't get stuck before using every edge
+    let last_edges: BTreeMap<char, usize> = loop {
+        let choice: BTreeMap<char, usize> = edges
+            .iter()
+            .filter(|(&nuc, _)| nuc != last)
+            .map(|(&nuc, next)| (nuc, rng.gen_range(0..next.len())))
+            .collect();
+        let leads_to_last = choice.keys().all(|&start| {
+            let mut nuc = start;
+            for _ in 0..choice.len() {
+                nuc = edges[&nuc][choice[&nuc]];
+                if nuc == last {
+                    return true;
+                }
+            }
+            false
+        });
+        if leads_to_last {
+            break choice;
+        }
+    };
+    for (nuc, next) in edges.iter_mut() {
+        if let Some(&index) = last_edges.get(nuc) {
+            let final_edge = next.len() - 1;
+            next.swap(index, final_edge);
+            next[..final_edge].shuffle(rng);
+        } else {
+            next.shuffle(rng);
+        }
+    }
+    let mut position: BTreeMap<char, usize> = BTreeMap::new();
+    let mut shuffled = String::with_capacity(sequence.len());
+    let mut nuc = nucs[0];
+    shuffled.push(nuc);
+    for _ in 1..nucs.len() {
+        let index = position.entry(nuc).or_insert(0);
+        nuc = edges[&nuc][*index];
+        *index += 1;
+        shuffled.push(nuc);
+    }
+    shuffled
+}
+
+pub(crate) fn shuffle_sequences<R: Rng + ?Sized>(sequences: &[String], rng: &mut R) -> Vec<String> {
+    sequences
+        .iter()
+        .map(|seq| dinucleotide_shuffle(seq, rng))
+        .collect()
+}
+
+/// How often a motif at least as good as the reported one was found in shuffled sequences
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Significance {
+    pub shuffles: usize,
+    /// fraction of shuffles, counting the real input as one, whose best motif scored at least as well
+    pub p_value: f64,
+    /// the p-value times the number of motifs found (a Bonferroni correction), at most 1
+    pub adjusted_p_value: f64,
+}
+
+impl Significance {
+    /// Compare the score of the reported motif against the scores found in each shuffle, where lower is better
+    ///
+    /// The p-value is left unadjusted until `adjust` is given the number of motifs found.
+    pub(crate) fn from_null(score: f64, null_scores: &[f64]) -> Significance {
+        let as_good = null_scores.iter().filter(|&&null| null <= score).count();
+        let p_value = (as_good + 1) as f64 / (null_scores.len() + 1) as f64;
+        Significance {
+            shuffles: null_scores.len(),
+            p_value,
+            adjusted_p_value: p_value,
+        }
+    }
+
+    /// Correct the p-value for the number of motifs the run found
+    pub(crate) fn adjust(&mut self, num_motifs: usize) {
+        self.adjusted_p_value = (self.p_value * num_motifs.max(1) as f64).min(1.0);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use rand::thread_rng;
+
+    use super::{dinucleotide_shuffle, Significance};
+
+    fn dinucleotide_counts(sequence: &str) -> HashMap<String, usize> {
+        let nucs: Vec<char> = sequence.chars().collect();
+        let mut counts = HashMap::new();
+        for pair in nucs.windows(2) {
+            *counts.entry(pair.iter().collect()).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    #[test]
+    pub fn test_dinucleotide_shuffle() {
+        let sequence = "ACGTTTGACGCAATTACTTGACGCAACATNNAGGA";
+        let mut rng = thread_rng();
+        for _ in 0..20 {
+            let shuffled = dinucleotide_shuffle(sequence, &mut rng);
+            assert_eq!(shuffled.len(), sequence.len());
+            assert_eq!(shuffled.chars().next(), sequence.chars().next());
+            assert_eq!(shuffled.chars().last(), sequence.chars().last());
+            assert_eq!(
+                dinucleotide_counts(&shuffled),
+                dinucleotide_counts(sequence)
+            );
+        }
+    }
+
+    #[test]
+    pub fn test_significance() {
+        let mut significance = Significance::from_null(2.0, &[5.0, 6.0, 1.0, 7.0]);
+        assert_eq!(significance.shuffles, 4);
+        assert!((significance.p_value - 0.4).abs() < 1e-9);
+        assert!((significance.adjusted_p_value - 0.4).abs() < 1e-9);
+        significance.adjust(2);
+        assert!((significance.adjusted_p_value - 0.8).abs() < 1e-9);
+    }
+
+    #[test]
+    pub fn test_adjusted_p_value_at_most_one() {
+        let mut significance = Significance::from_null(2.0, &[1.0, 1.0, 5.0]);
+        significance.adjust(3);
+        assert!((significance.p_value - 0.75).abs() < 1e-9);
+        assert_eq!(significance.adjusted_p_value, 1.0);
+    }
+}
@@ -0,0 +1,312 @@
+use std::fmt::Display;
+
+use clap::ValueEnum;
+use rand::distributions::WeightedIndex;
+use rand::prelude::*;
+
+use crate::background::Background;
+use crate::scoring::ScoringMethod;
+use crate::sequence::{candidate_kmers, Candidate, MotifInstance};
+use crate::{generate_probability, generate_profile_given_motif_matrix, Error};
+use serde::Serialize;
+
+/// How many occurrences of the motif each sequence is assumed to contain
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SiteModel {
+    /// one occurrence per sequence
+    #[default]
+    #[value(name = "oops")]
+    Oops,
+    /// zero or one occurrence per sequence
+    #[value(name = "zoops")]
+    Zoops,
+    /// any number of non-overlapping occurrences per sequence
+    #[value(name = "tcm")]
+    Tcm,
+}
+
+impl Display for SiteModel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SiteModel::Oops => write!(f, "oops"),
+            SiteModel::Zoops => write!(f, "zoops"),
+            SiteModel::Tcm => write!(f, "tcm"),
+        }
+    }
+}
+
+impl SiteModel {
+    /// Pick the sites of one sequence that best fit the profile
+    #[tracing::instrument(skip_all)]
+    pub(crate) fn most_probable_sites(
+        &self,
+        kmers: &[Candidate],
+        profile: &[Vec<f64>],
+        background: &Background,
+    ) -> Vec<MotifInstance> {
+        if kmers.is_empty() {
+            return vec![];
+        }
+        let ratios = likelihood_ratios(kmers, profile, background);
+        // a site could be at any of the candidate positions, so it has to beat the background by more
+        // than the number of candidates before it is more likely to be a site than chance
+        let threshold = kmers.len() as f64;
+        match self {
+            SiteModel::Oops => {
+                let best = max_index(&ratios);
+                vec![kmers[best].instance.clone()]
+            }
+            SiteModel::Zoops => {
+                // the sequence contains a site when the average ratio favours the motif over the background
+                let best = max_index(&ratios);
+                if ratios.iter().sum::<f64>() > threshold {
+                    vec![kmers[best].instance.clone()]
+                } else {
+                    vec![]
+                }
+            }
+            SiteModel::Tcm => {
+                // greedily take the most likely sites that beat the threshold and don't overlap
+                let k = kmers[0].instance.kmer.len();
+                let mut order: Vec<usize> = (0..kmers.len())
+                    .filter(|&i| ratios[i] > threshold)
+                    .collect();
+                order.sort_by(|&a, &b| ratios[b].total_cmp(&ratios[a]));
+                let mut chosen: Vec<usize> = vec![];
+                for i in order {
+                    let offset = kmers[i].offset;
+                    let overlaps = chosen.iter().any(|&j| {
+                        let other = kmers[j].offset;
+                        offset < other + k && other < offset + k
+                    });
+                    if !overlaps {
+                        chosen.push(i);
+                    }
+                }
+                chosen.sort_unstable();
+                chosen
+                    .into_iter()
+                    .map(|i| kmers[i].instance.clone())
+                    .collect()
+            }
+        }
+    }
+
+    /// Sample the sites of one sequence in proportion to how well they fit the profile
+    #[tracing::instrument(skip_all)]
+    pub(crate) fn profile_randomly_generated_sites<R: Rng + ?Sized>(
+        &self,
+        kmers: &[Candidate],
+        profile: &[Vec<f64>],
+        background: &Background,
+        rng: &mut R,
+    ) -> Option<Vec<MotifInstance>> {
+        if kmers.is_empty() {
+            return None;
+        }
+        let ratios = likelihood_ratios(kmers, profile, background);
+        // same prior as most_probable_sites: a site is as likely as not once its ratio reaches the number of candidates
+        let threshold = kmers.len() as f64;
+        match self {
+            SiteModel::Oops => {
+                let dist = WeightedIndex::new(&ratios).ok()?;
+                Some(vec![kmers[dist.sample(rng)].instance.clone()])
+            }
+            SiteModel::Zoops => {
+                // the extra last weight is the chance that the sequence has no site at all
+                let mut weights = ratios.clone();
+                weights.push(threshold);
+                let dist = WeightedIndex::new(&weights).ok()?;
+                let choice = dist.sample(rng);
+                Some(
+                    kmers
+                        .get(choice)
+                        .map(|kmer| kmer.instance.clone())
+                        .into_iter()
+                        .collect(),
+                )
+            }
+            SiteModel::Tcm => {
+                // walk along the sequence and keep each non-overlapping site with probability r / (r + threshold)
+                let k = kmers[0].instance.kmer.len();
+                let mut sites = vec![];
+                let mut next_free = 0;
+                for (kmer, ratio) in kmers.iter().zip(ratios) {
+                    if kmer.offset < next_free {
+                        continue;
+                    }
+                    if rng.gen_bool((ratio / (ratio + threshold)).clamp(0.0, 1.0)) {
+                        sites.push(kmer.instance.clone());
+                        next_free = kmer.offset + k;
+                    }
+                }
+                Some(sites)
+            }
+        }
+    }
+}
+
+impl SiteModel {
+    /// Score of a set of sites, lower is better
+    ///
+    /// With one site per sequence this is the chosen score of the sites. When the number of sites
+    /// varies that would favour keeping as few sites as possible, so the other models use the negative
+    /// log likelihood ratio of the sites under their own profile, where every site pays for the
+    /// number of places in its sequence it could have been found.
+    #[tracing::instrument(skip_all)]
+    pub(crate) fn score(
+        &self,
+        sites: &[Vec<MotifInstance>],
+        candidates: &[Vec<Candidate>],
+        scoring: ScoringMethod,
+        background: &Background,
+    ) -> Result<f64, Error> {
+        let motifs: Vec<&MotifInstance> = sites.iter().flatten().collect();
+        if motifs.is_empty() {
+            return Ok(f64::MAX);
+        }
+        match self {
+            SiteModel::Oops => Ok(scoring.cost(&motifs, background)),
+            SiteModel::Zoops | SiteModel::Tcm => {
+                let profile = generate_profile_given_motif_matrix(&motifs, true)?;
+                let mut score = 0.0;
+                for (sequence_sites, kmers) in sites.iter().zip(candidates) {
+                    if sequence_sites.is_empty() {
+                        continue;
+                    }
+                    let threshold = (kmers.len() as f64).log2();
+                    for ratio in likelihood_ratios(sequence_sites, &profile, background) {
+                        score -= ratio.log2() - threshold;
+                    }
+                }
+                Ok(score)
+            }
+        }
+    }
+}
+
+impl SiteModel {
+    /// Score of sites placed in the sequences, as `score` gives it for the sites grouped by their sequence
+    #[tracing::instrument(skip_all)]
+    pub(crate) fn score_sites(
+        &self,
+        motifs: &[MotifInstance],
+        sequences: &[String],
+        k: usize,
+        both_strands: bool,
+        scoring: ScoringMethod,
+        background: &Background,
+    ) -> Result<f64, Error> {
+        let candidates: Vec<Vec<Candidate>> = sequences
+            .iter()
+            .enumerate()
+            .map(|(index, seq)| candidate_kmers(index, seq, k, both_strands))
+            .collect();
+        let mut sites: Vec<Vec<MotifInstance>> = vec![vec![]; sequences.len()];
+        for motif in motifs {
+            if let Some(sequence_sites) = motif
+                .position
+                .and_then(|position| sites.get_mut(position.sequence))
+            {
+                sequence_sites.push(motif.clone());
+            }
+        }
+        self.score(&sites, &candidates, scoring, background)
+    }
+}
+
+/// Best score, sites of every sequence and score after every iteration of a run
+pub(crate) type Run = (f64, Vec<Vec<MotifInstance>>, Vec<f64>);
+
+/// Candidate sites of every sequence, or an error when no sequence has any
+#[tracing::instrument(skip(dna))]
+pub(crate) fn sequence_candidates(
+    dna: &[String],
+    k: usize,
+    both_strands: bool,
+) -> Result<Vec<Vec<Candidate>>, Error> {
+    let candidates: Vec<Vec<Candidate>> = dna
+        .iter()
+        .enumerate()
+        .map(|(index, seq)| candidate_kmers(index, seq, k, both_strands))
+        .collect();
+    if candidates.iter().all(|kmers| kmers.is_empty()) {
+        // every kmer has been masked or the sequences are shorter than k
+        return Err(Error::NoMotifsFound);
+    }
+    Ok(candidates)
+}
+
+/// Random sites to start a run from
+#[tracing::instrument(skip_all)]
+pub(crate) fn random_initial_sites<R: Rng + ?Sized>(
+    candidates: &[Vec<Candidate>],
+    rng: &mut R,
+) -> Vec<Vec<MotifInstance>> {
+    // every sequence starts with one random site regardless of the site model
+    candidates
+        .iter()
+        .map(|kmers| {
+            if kmers.is_empty() {
+                return vec![];
+            }
+            let start_index = rng.gen_range(0..kmers.len());
+            vec![kmers[start_index].instance.clone()]
+        })
+        .collect()
+}
+
+#[tracing::instrument(skip_all)]
+fn likelihood_ratios<S: AsRef<str>>(
+    kmers: &[S],
+    profile: &[Vec<f64>],
+    background: &Background,
+) -> Vec<f64> {
+    // probability of each kmer under the profile relative to the background
+    kmers
+        .iter()
+        .map(|kmer| {
+            let kmer = kmer.as_ref();
+            generate_probability(kmer, profile) / background.probability(kmer)
+        })
+        .collect()
+}
+
+fn max_index(values: &[f64]) -> usize {
+    let mut best = 0;
+    for (i, value) in values.iter().enumerate() {
+        if *value > values[best] {
+            best = i;
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod test {
+    use super::SiteModel;
+    use crate::background::Background;
+    use crate::generate_profile_given_motif_matrix;
+    use crate::sequence::candidate_kmers;
+
+    #[test]
+    pub fn test_most_probable_sites() {
+        let profile = generate_profile_given_motif_matrix(&["TTGACGCA"; 4], true).unwrap();
+        let without_site = candidate_kmers(0, "CGCCTTTACTTGCTGTGTCCACCCCATCGG", 8, false);
+        let two_sites = candidate_kmers(1, "GCTTTGACGCAATTACTTGACGCAACAT", 8, false);
+
+        let background = Background::uniform();
+        let sites = SiteModel::Oops.most_probable_sites(&without_site, &profile, &background);
+        assert_eq!(sites.len(), 1);
+        let sites = SiteModel::Zoops.most_probable_sites(&without_site, &profile, &background);
+        assert!(sites.is_empty());
+        let sites = SiteModel::Zoops.most_probable_sites(&two_sites, &profile, &background);
+        assert_eq!(sites.len(), 1);
+        let sites = SiteModel::Tcm.most_probable_sites(&two_sites, &profile, &background);
+        assert_eq!(sites.len(), 2);
+        assert!(sites.iter().all(|site| site.kmer == "TTGACGCA"));
+        let sites = SiteModel::Tcm.most_probable_sites(&without_site, &profile, &background);
+        assert!(sites.is_empty());
+    }
+}
@@ -8,8 +8,18 @@ use chrono::{DateTime, Utc};
 use tracing::trace;
 
 use crate::{
-    command::{Commands, Summary},
-    Error,
+    alignment::Hit,
+    annotation::{
+        hit_annotations, instance_annotations, write_bed, write_gff3, Annotation, OutputFormat,
+    },
+    command::{Commands, GlobalOpts, Summary},
+    generate_profile_given_motif_matrix,
+    html::render_report,
+    meme::{write_meme, MemeMotif},
+    motif_io::{write_matrices, MotifFormat, MotifMatrix},
+    scan::ScanHit,
+    sequence::{MotifInstance, SequenceRecord},
+    Error, SearchOptions,
 };
 
 pub fn generate_vector_space_delimited<T: Display>(vec: &[T]) -> String {
@@ -21,11 +31,40 @@ pub fn generate_vector_space_delimited<T: Display>(vec: &[T]) -> String {
     string
 }
 
-#[tracing::instrument]
-pub fn write_file_header(
-    file: &mut fs::File,
-    k: usize,
-    num_entries: usize,
+/// Hits grouped by sequence with their alignments, followed by the number of matches
+pub fn format_hits(hits: &[Hit]) -> String {
+    let mut string = String::new();
+    let mut previous_sequence = None;
+    for hit in hits {
+        if previous_sequence != Some(hit.sequence) {
+            string.push_str(&format!("Sequence: {}\n", hit.id));
+            previous_sequence = Some(hit.sequence);
+        }
+        string.push_str(&format!(
+            "Hit found in range: {}..{} (distance: {})\n{}\n",
+            hit.start, hit.end, hit.distance, hit.alignment
+        ));
+    }
+    let sequences_with_hits = unique_sequences(hits);
+    string.push_str(&format!("Number of matches: {}\n", hits.len()));
+    string.push_str(&format!(
+        "Number of sequences with matches: {}\n",
+        sequences_with_hits
+    ));
+    string
+}
+
+fn unique_sequences(hits: &[Hit]) -> usize {
+    let mut sequences: Vec<usize> = hits.iter().map(|hit| hit.sequence).collect();
+    sequences.dedup();
+    sequences.len()
+}
+
+#[tracing::instrument(skip(file))]
+pub fn write_file_header<W: Write>(
+    file: &mut W,
+    global_opts: &GlobalOpts,
+    options: &SearchOptions,
     command: &Commands,
     dt: DateTime<Utc>,
 ) -> io::Result<()> {
@@ -34,12 +73,43 @@ pub fn write_file_header(
     let command_string = match command {
         Commands::Randomized { .. } => "Randomized Motif Search",
         Commands::GibbsSampler { .. } => "Gibbs Sampler",
+        Commands::ExpectationMaximization { .. } => "Expectation Maximization",
         Commands::MedianString => "Median String",
+        Commands::Discriminative { .. } => "Discriminative Motif Search",
+        Commands::Ensemble { .. } => "Ensemble",
+        Commands::Scan { .. } => "Scan",
+        Commands::Logo { .. } => "Logo",
         Commands::FindMotif { .. } => "Find Motif",
     };
     writeln!(file, "Command: {}", command_string)?;
-    writeln!(file, "k: {}", k)?;
-    writeln!(file, "number of entries: {}", num_entries)?;
+    writeln!(file, "k: {}", global_opts.k)?;
+    writeln!(
+        file,
+        "number of entries: {}",
+        global_opts.num_entries.unwrap_or_default()
+    )?;
+    writeln!(file, "number of motifs: {}", global_opts.num_motifs)?;
+    if global_opts.shuffles > 0 {
+        writeln!(file, "shuffles: {}", global_opts.shuffles)?;
+    }
+    writeln!(file, "both strands: {}", options.both_strands)?;
+    writeln!(file, "site model: {}", options.site_model)?;
+    writeln!(file, "scoring: {}", options.scoring)?;
+    writeln!(
+        file,
+        "IUPAC thresholds: single {:.2} pair {:.2}",
+        global_opts.iupac_single, global_opts.iupac_pair
+    )?;
+    writeln!(file, "background order: {}", options.background.order())?;
+    let frequencies = options.background.frequencies();
+    writeln!(
+        file,
+        "background frequencies: A {:.3} C {:.3} G {:.3} T {:.3}",
+        frequencies[0], frequencies[1], frequencies[2], frequencies[3]
+    )?;
+    if let Some(seed) = options.seed {
+        writeln!(file, "seed: {}", seed)?;
+    }
     match command {
         Commands::Randomized { num_runs } => {
             writeln!(file, "runs: {}", num_runs)?;
@@ -47,11 +117,55 @@ pub fn write_file_header(
         Commands::GibbsSampler {
             num_runs,
             num_iterations,
+        }
+        | Commands::ExpectationMaximization {
+            num_runs,
+            num_iterations,
         } => {
             writeln!(file, "runs: {}", num_runs)?;
             writeln!(file, "iterations: {}", num_iterations)?;
         }
         Commands::MedianString => {}
+        Commands::Discriminative { control } => {
+            writeln!(file, "control: {}", control)?;
+        }
+        Commands::Ensemble {
+            algorithms,
+            num_runs,
+            num_iterations,
+            max_distance,
+            min_support,
+        } => {
+            writeln!(
+                file,
+                "algorithms: {}",
+                generate_vector_space_delimited(algorithms)
+            )?;
+            writeln!(file, "runs: {}", num_runs)?;
+            writeln!(file, "iterations: {}", num_iterations)?;
+            writeln!(file, "max distance: {}", max_distance)?;
+            writeln!(file, "min support: {}", min_support)?;
+        }
+        Commands::Scan {
+            matrix,
+            matrix_format,
+            min_score,
+            max_p_value,
+        } => {
+            writeln!(file, "matrix: {}", matrix)?;
+            if let Some(matrix_format) = matrix_format {
+                writeln!(file, "matrix format: {}", matrix_format)?;
+            }
+            if let Some(min_score) = min_score {
+                writeln!(file, "minimum score: {}", min_score)?;
+            }
+            if let Some(max_p_value) = max_p_value {
+                writeln!(file, "maximum p-value: {}", max_p_value)?;
+            }
+        }
+        Commands::Logo { matrix, .. } => {
+            writeln!(file, "matrix: {}", matrix)?;
+        }
         Commands::FindMotif { motif, distance } => {
             writeln!(file, "motif: {}", motif)?;
             writeln!(file, "distance: {}", distance)?;
@@ -67,10 +181,15 @@ pub fn create_output_file(
     save_flag: &Option<String>,
     k: usize,
     timestamp: i64,
+    format: OutputFormat,
 ) -> Result<(File, String), Error> {
-    let save_path = save_flag
-        .clone()
-        .unwrap_or_else(|| format!("MotifFinder-output-{timestamp}-{}.txt", k));
+    let save_path = save_flag.clone().unwrap_or_else(|| {
+        format!(
+            "MotifFinder-output-{timestamp}-{}.{}",
+            k,
+            format.extension()
+        )
+    });
     let file = match fs::File::create(&save_path) {
         Ok(file) => file,
         Err(_err) => return Err(Error::IOError),
@@ -79,50 +198,289 @@ pub fn create_output_file(
 }
 pub fn output_results_to_file(
     file: &mut fs::File,
-    motifs: &[String],
-    summary: &Summary,
+    results: &[(Vec<MotifInstance>, Summary)],
+    records: &[SequenceRecord],
     command: Commands,
 ) -> Result<DateTime<Utc>, Error> {
-    let Summary {
-        consensus_string,
-        best_motif_score,
-        best_motif,
-        unique_motifs,
-    } = summary;
     trace!("Writing results to file");
     let dt_end = Utc::now();
     writeln!(file, "End time: {}", dt_end.format("%Y-%m-%d %H:%M:%S"))
         .map_err(|_| Error::IOError)?;
-    match command {
-        Commands::FindMotif { .. } => {}
-        _ => {
-            writeln!(file, "Consensus string: {}", consensus_string).map_err(|_| Error::IOError)?;
-            writeln!(file, "Unique motifs: {}", unique_motifs).map_err(|_| Error::IOError)?;
-            if let Some(best_motif) = best_motif {
-                writeln!(file, "Best motif: {}", best_motif).map_err(|_| Error::IOError)?;
+    for (i, (motifs, summary)) in results.iter().enumerate() {
+        let Summary {
+            consensus_string,
+            degenerate_consensus,
+            best_motif_score,
+            top_motifs: _,
+            best_motif,
+            unique_motifs,
+            site_posteriors,
+            motif_score,
+            enrichment,
+            significance,
+            hits,
+            score_trace: _,
+            ensemble,
+        } = summary;
+        if i > 0 {
+            // the last motif of the previous section is written without a newline
+            writeln!(file).map_err(|_| Error::IOError)?;
+        }
+        if results.len() > 1 {
+            writeln!(file, "Motif {} of {}", i + 1, results.len()).map_err(|_| Error::IOError)?;
+        }
+        match command {
+            Commands::FindMotif { .. } => {
+                if let Some(hits) = hits {
+                    write!(file, "{}", format_hits(hits)).map_err(|_| Error::IOError)?;
+                }
             }
-            if let Some(best_motif_score) = best_motif_score {
-                writeln!(file, "Best motif score: {}", best_motif_score)
+            _ => {
+                writeln!(file, "Consensus string: {}", consensus_string)
+                    .map_err(|_| Error::IOError)?;
+                writeln!(
+                    file,
+                    "Degenerate consensus (IUPAC): {}",
+                    degenerate_consensus
+                )
+                .map_err(|_| Error::IOError)?;
+                writeln!(file, "Unique motifs: {}", unique_motifs).map_err(|_| Error::IOError)?;
+                if let Some((scoring, score)) = motif_score {
+                    writeln!(file, "Score ({}): {:.3}", scoring, score)
+                        .map_err(|_| Error::IOError)?;
+                }
+                if let Some(enrichment) = enrichment {
+                    writeln!(
+                        file,
+                        "Enrichment: {}/{} input, {}/{} control, p-value {:.3e}",
+                        enrichment.input_count,
+                        enrichment.input_total,
+                        enrichment.control_count,
+                        enrichment.control_total,
+                        enrichment.p_value
+                    )
                     .map_err(|_| Error::IOError)?;
+                }
+                for member in ensemble.iter().flatten() {
+                    let strand = if member.reverse_complement {
+                        " (reverse complement)"
+                    } else {
+                        ""
+                    };
+                    writeln!(
+                        file,
+                        "Found by {}: {}{}, score {:.3}",
+                        member.algorithm, member.consensus, strand, member.score
+                    )
+                    .map_err(|_| Error::IOError)?;
+                }
+                if let Some(significance) = significance {
+                    writeln!(
+                        file,
+                        "Significance: p-value {:.3e}, adjusted p-value {:.3e} ({} shuffles)",
+                        significance.p_value, significance.adjusted_p_value, significance.shuffles
+                    )
+                    .map_err(|_| Error::IOError)?;
+                }
+                if let Some(best_motif) = best_motif {
+                    writeln!(file, "Best motif: {}", best_motif).map_err(|_| Error::IOError)?;
+                }
+                if let Some(best_motif_score) = best_motif_score {
+                    writeln!(file, "Best motif score: {}", best_motif_score)
+                        .map_err(|_| Error::IOError)?;
+                }
             }
         }
+
+        writeln!(
+            file,
+            "_________________________________________________________________________________________"
+        )
+        .map_err(|_| Error::IOError)?;
+        write_motifs(file, motifs, records, site_posteriors.as_deref())?;
     }
+    Ok(dt_end)
+}
 
+/// Sites of every motif of the results, or the matches of the pattern for find_motif
+pub fn results_annotations(
+    results: &[(Vec<MotifInstance>, Summary)],
+    records: &[SequenceRecord],
+    command: &Commands,
+) -> Vec<Annotation> {
+    results
+        .iter()
+        .flat_map(|(motifs, summary)| match (command, &summary.hits) {
+            (Commands::FindMotif { .. }, Some(hits)) => {
+                hit_annotations(hits, &summary.consensus_string, records)
+            }
+            _ => instance_annotations(
+                motifs,
+                &summary.consensus_string,
+                records,
+                summary.site_posteriors.as_deref(),
+            ),
+        })
+        .collect()
+}
+
+pub fn output_annotations_to_file(
+    file: &mut fs::File,
+    annotations: &[Annotation],
+    format: OutputFormat,
+) -> Result<DateTime<Utc>, Error> {
+    trace!("Writing {} annotations to file", format);
+    match format {
+        OutputFormat::Bed => write_bed(file, annotations).map_err(|_| Error::IOError)?,
+        OutputFormat::Gff3 => write_gff3(file, annotations).map_err(|_| Error::IOError)?,
+        _ => return Err(Error::InvalidInputError),
+    }
+    Ok(Utc::now())
+}
+
+/// Profile of every motif of the results, or of the pattern for find_motif, named after its degenerate consensus
+pub fn results_meme_motifs(
+    results: &[(Vec<MotifInstance>, Summary)],
+    command: &Commands,
+) -> Result<Vec<MemeMotif>, Error> {
+    results
+        .iter()
+        .map(|(motifs, summary)| {
+            let (profile, sites) = match (command, &summary.hits) {
+                (Commands::FindMotif { .. }, Some(hits)) => {
+                    // the pattern may hold degenerate codes, which generate_profile_given_motif_matrix ignores
+                    let matrix = MotifMatrix::from_sites(&summary.degenerate_consensus, motifs)?;
+                    (matrix.frequencies(), hits.len())
+                }
+                _ => (
+                    generate_profile_given_motif_matrix(motifs, false)?,
+                    motifs.len(),
+                ),
+            };
+            Ok(MemeMotif {
+                name: summary.degenerate_consensus.clone(),
+                profile,
+                sites,
+                // the adjusted p-value of a shuffled search isn't an E-value, so it isn't written as one
+                e_value: None,
+            })
+        })
+        .collect()
+}
+
+/// Count matrix of the sites of every motif of the results, or of the pattern for find_motif, named after its degenerate consensus
+pub fn results_matrices(
+    results: &[(Vec<MotifInstance>, Summary)],
+) -> Result<Vec<MotifMatrix>, Error> {
+    results
+        .iter()
+        .map(|(motifs, summary)| MotifMatrix::from_sites(&summary.degenerate_consensus, motifs))
+        .collect()
+}
+
+pub fn output_matrices_to_file(
+    file: &mut fs::File,
+    matrices: &[MotifMatrix],
+    format: MotifFormat,
+) -> Result<DateTime<Utc>, Error> {
+    trace!("Writing matrices to file in {} format", format);
+    write_matrices(file, matrices, format).map_err(|_| Error::IOError)?;
+    Ok(Utc::now())
+}
+
+pub fn output_meme_to_file(
+    file: &mut fs::File,
+    motifs: &[MemeMotif],
+    background: [f64; 4],
+    both_strands: bool,
+) -> Result<DateTime<Utc>, Error> {
+    trace!("Writing motifs to file in MEME format");
+    write_meme(file, motifs, background, both_strands).map_err(|_| Error::IOError)?;
+    Ok(Utc::now())
+}
+
+/// Write the HTML report of the run, with the parameters of the text report header
+pub fn output_html_report_to_file(
+    file: &mut fs::File,
+    global_opts: &GlobalOpts,
+    options: &SearchOptions,
+    results: &[(Vec<MotifInstance>, Summary)],
+    records: &[SequenceRecord],
+    command: &Commands,
+    dt: DateTime<Utc>,
+) -> Result<DateTime<Utc>, Error> {
+    trace!("Writing HTML report to file");
+    let dt_end = Utc::now();
+    let mut header = vec![];
+    write_file_header(&mut header, global_opts, options, command, dt)
+        .map_err(|_| Error::IOError)?;
+    writeln!(header, "End time: {}", dt_end.format("%Y-%m-%d %H:%M:%S"))
+        .map_err(|_| Error::IOError)?;
+    let header = String::from_utf8(header).map_err(|_| Error::IOError)?;
+    let report = render_report(&header, results, records, command)?;
+    file.write_all(report.as_bytes())
+        .map_err(|_| Error::IOError)?;
+    Ok(dt_end)
+}
+
+pub fn output_scan_results_to_file(
+    file: &mut fs::File,
+    hits: &[ScanHit],
+) -> Result<DateTime<Utc>, Error> {
+    trace!("Writing scan results to file");
+    let dt_end = Utc::now();
+    writeln!(file, "End time: {}", dt_end.format("%Y-%m-%d %H:%M:%S"))
+        .map_err(|_| Error::IOError)?;
+    writeln!(file, "Number of sites: {}", hits.len()).map_err(|_| Error::IOError)?;
     writeln!(
         file,
         "_________________________________________________________________________________________"
     )
     .map_err(|_| Error::IOError)?;
-    write_motifs(file, motifs)?;
+    writeln!(
+        file,
+        "motif\tsequence\tstart\tend\tstrand\tscore\tp-value\tsite"
+    )
+    .map_err(|_| Error::IOError)?;
+    for hit in hits {
+        writeln!(
+            file,
+            "{}\t{}\t{}\t{}\t{}\t{:.3}\t{:.3e}\t{}",
+            hit.motif, hit.id, hit.start, hit.end, hit.strand, hit.score, hit.p_value, hit.site
+        )
+        .map_err(|_| Error::IOError)?;
+    }
     Ok(dt_end)
 }
 
 #[tracing::instrument(skip_all)]
-fn write_motifs(file: &mut fs::File, motifs: &[String]) -> Result<(), Error> {
+fn write_motifs(
+    file: &mut fs::File,
+    motifs: &[MotifInstance],
+    records: &[SequenceRecord],
+    site_posteriors: Option<&[f64]>,
+) -> Result<(), Error> {
     trace!("Writing motifs to file");
     for (i, motif) in motifs.iter().enumerate() {
-        let motif = motif.trim();
-        writeln!(file, ">motif {}", i + 1).map_err(|_| Error::IOError)?;
+        write!(file, ">motif {} strand={}", i + 1, motif.strand).map_err(|_| Error::IOError)?;
+        if let Some(position) = motif.position {
+            let id = records
+                .get(position.sequence)
+                .map_or_else(|| position.sequence.to_string(), |record| record.id.clone());
+            write!(
+                file,
+                " sequence={} start={} end={}",
+                id,
+                position.offset,
+                position.offset + motif.kmer.len()
+            )
+            .map_err(|_| Error::IOError)?;
+        }
+        if let Some(posterior) = site_posteriors.and_then(|posteriors| posteriors.get(i)) {
+            write!(file, " posterior={:.4}", posterior).map_err(|_| Error::IOError)?;
+        }
+        writeln!(file).map_err(|_| Error::IOError)?;
+        let motif = motif.kmer.trim();
         if i == motifs.len() - 1 {
             write!(file, "{}", motif).map_err(|_| Error::IOError)?;
         } else {